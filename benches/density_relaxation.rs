@@ -0,0 +1,59 @@
+//! Compares the scalar and chunked density relaxation kernels.
+//!
+//! Run with `cargo bench --bench density_relaxation`.
+
+use std::time::{Duration, Instant};
+
+use fluid_sim::*;
+
+const STEPS: usize = 200;
+
+fn build(kernel: RelaxationKernel, particle_num: usize) -> Simulation {
+    let columns = f32::sqrt(particle_num as f32) as usize;
+
+    let particles = (0..particle_num)
+        .map(|i| Particle::new(
+            Vec2 { x: (i % columns) as f32 * 7. + 17., y: (i / columns) as f32 * 7. + 17. },
+            Vec2 { x: 0., y: 0. },
+        ))
+        .collect();
+
+    let mut simulation = SimulationBuilder::default()
+        .with_boundaries(Rect {
+            min: Vec2 { x: 0., y: 0. },
+            max: Vec2 { x: 800., y: 600. },
+        })
+        .with_kernel(kernel)
+        .with_particles(particles)
+        .build();
+    simulation.init();
+
+    simulation
+}
+
+fn run(kernel: RelaxationKernel, particle_num: usize) -> Duration {
+    let mut simulation = build(kernel, particle_num);
+
+    let start = Instant::now();
+    for _ in 0..STEPS {
+        simulation.step(1. / 60.);
+    }
+
+    start.elapsed()
+}
+
+fn main() {
+    println!("{STEPS} steps per run");
+
+    for particle_num in [280, 800, 2000] {
+        let scalar = run(RelaxationKernel::Scalar, particle_num);
+        let chunked = run(RelaxationKernel::Chunked, particle_num);
+
+        println!(
+            "{particle_num:>5} particles: scalar {:>9.2?}, chunked {:>9.2?}, speed-up {:.2}x",
+            scalar,
+            chunked,
+            scalar.as_secs_f64() / chunked.as_secs_f64(),
+        );
+    }
+}
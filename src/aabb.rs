@@ -0,0 +1,23 @@
+use crate::{Float, Vec3};
+
+/// Axis-aligned box, the 3D counterpart of `Rect`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Aabb<T = f32> {
+    pub min: Vec3<T>,
+    pub max: Vec3<T>,
+}
+
+impl<T: Float> Aabb<T> {
+    pub fn width(self) -> T {
+        self.max.x - self.min.x
+    }
+
+    pub fn height(self) -> T {
+        self.max.y - self.min.y
+    }
+
+    pub fn depth(self) -> T {
+        self.max.z - self.min.z
+    }
+}
@@ -0,0 +1,236 @@
+//! Runs a scene without a window and writes frames and statistics to disk.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::process;
+use std::time::Instant;
+
+use fluid_sim::*;
+
+const USAGE: &str = "usage: fluid_sim_cli <scene.json> [options]
+
+options:
+    --steps <n>       steps to run (default 600)
+    --dt <seconds>    time step (default 0.016666)
+    --threads <n>     worker threads (default: one per core)
+    --format <f>      frame format: csv, vtk, vtu, ply, json, snapshot or png (default csv)
+    --interval <n>    steps between frames, 0 for no frames (default 10)
+    --out <dir>       output directory (default out)
+    --trajectory <f>  also record a trajectory file
+    --trajectory-interval <n>
+                      steps between trajectory frames, at least 1 (default 10)
+    --style <s>       png particle style: discs or metaballs (default discs)
+    --scale <px>      png pixels per simulation unit (default 1)";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Export(ExportFormat),
+    Json,
+    Snapshot,
+    Png,
+}
+
+#[derive(Debug, Clone)]
+struct Options {
+    scene: PathBuf,
+    steps: usize,
+    dt: f32,
+    threads: Option<usize>,
+    format: Format,
+    interval: usize,
+    out: PathBuf,
+    trajectory: Option<PathBuf>,
+    trajectory_interval: usize,
+    render: RenderOptions,
+}
+
+fn main() {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{err}\n\n{USAGE}");
+            process::exit(2);
+        }
+    };
+
+    if let Err(err) = run(&options) {
+        eprintln!("error: {err}");
+        process::exit(1);
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut scene = None;
+    let mut options = Options {
+        scene: PathBuf::new(),
+        steps: 600,
+        dt: 1. / 60.,
+        threads: None,
+        format: Format::Export(ExportFormat::Csv),
+        interval: 10,
+        out: PathBuf::from("out"),
+        trajectory: None,
+        trajectory_interval: 10,
+        render: RenderOptions::default(),
+    };
+
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            if scene.replace(PathBuf::from(&arg)).is_some() {
+                return Err(format!("unexpected argument {arg}"));
+            }
+            continue;
+        }
+
+        let value = args.next().ok_or_else(|| format!("missing value for {arg}"))?;
+        let invalid = || format!("invalid value for {arg}: {value}");
+
+        match arg.as_str() {
+            "--steps" => options.steps = value.parse().map_err(|_| invalid())?,
+            "--dt" => {
+                options.dt = value.parse().map_err(|_| invalid())?;
+
+                if !options.dt.is_finite() || options.dt <= 0. {
+                    return Err(invalid());
+                }
+            }
+            "--threads" => options.threads = Some(value.parse().map_err(|_| invalid())?),
+            "--interval" => options.interval = value.parse().map_err(|_| invalid())?,
+            "--out" => options.out = PathBuf::from(&value),
+            "--trajectory" => options.trajectory = Some(PathBuf::from(&value)),
+            "--trajectory-interval" => {
+                options.trajectory_interval = value.parse().map_err(|_| invalid())?;
+
+                if options.trajectory_interval == 0 {
+                    return Err(invalid());
+                }
+            }
+            "--format" => {
+                options.format = match value.as_str() {
+                    "csv" => Format::Export(ExportFormat::Csv),
+                    "vtk" => Format::Export(ExportFormat::Vtk),
+                    "vtu" => Format::Export(ExportFormat::Vtu),
+                    "ply" => Format::Export(ExportFormat::Ply),
+                    "json" => Format::Json,
+                    "snapshot" => Format::Snapshot,
+                    "png" => Format::Png,
+                    _ => return Err(invalid()),
+                }
+            }
+            "--scale" => {
+                options.render.scale = value.parse().map_err(|_| invalid())?;
+
+                if !options.render.scale.is_finite() || options.render.scale <= 0. {
+                    return Err(invalid());
+                }
+            }
+            "--style" => {
+                options.render.particle_style = match value.as_str() {
+                    "discs" => ParticleStyle::Discs { radius: 3. },
+                    "metaballs" => ParticleStyle::Metaballs { radius: 12., threshold: 0.6 },
+                    _ => return Err(invalid()),
+                }
+            }
+            _ => return Err(format!("unknown option {arg}")),
+        }
+    }
+
+    options.scene = scene.ok_or("missing scene file")?;
+    Ok(options)
+}
+
+fn run(options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(threads) = options.threads {
+        #[cfg(feature = "parallel")]
+        rayon::ThreadPoolBuilder::new().num_threads(threads).build_global()?;
+
+        #[cfg(not(feature = "parallel"))]
+        if threads > 1 {
+            eprintln!("warning: built without the parallel feature, running on one thread");
+        }
+    }
+
+    let mut simulation = SimulationBuilder::from_scene_file(&options.scene)?.build();
+    simulation.init();
+
+    fs::create_dir_all(&options.out)?;
+
+    let mut recorder = match &options.trajectory {
+        Some(path) => Some(TrajectoryRecorder::create(path, simulation.boundaries, options.trajectory_interval, TrajectoryEncoding::Full)?),
+        None => None,
+    };
+
+    let mut stats = BufWriter::new(File::create(options.out.join("stats.csv"))?);
+    writeln!(stats, "step,time,particles,mean_speed,max_speed,kinetic_energy,step_seconds")?;
+
+    for step in 0..=options.steps {
+        let start = Instant::now();
+
+        if step > 0 {
+            simulation.step(options.dt);
+        }
+
+        let step_seconds = start.elapsed().as_secs_f64();
+        write_stats(&mut stats, step, &simulation, step_seconds)?;
+
+        if options.interval > 0 && step % options.interval == 0 {
+            write_frame(options, step / options.interval, &simulation)?;
+        }
+
+        if let Some(recorder) = &mut recorder {
+            recorder.record(&simulation)?;
+        }
+    }
+
+    if let Some(recorder) = recorder {
+        recorder.finish()?;
+    }
+
+    stats.flush()?;
+    println!("ran {} steps, wrote output to {}", options.steps, options.out.display());
+
+    Ok(())
+}
+
+fn write_stats(writer: &mut impl Write, step: usize, simulation: &Simulation, step_seconds: f64) -> io::Result<()> {
+    let particles = simulation.particles();
+    let speeds = particles.iter().map(|particle| particle.vel.dist() as f64);
+
+    let max_speed = speeds.clone().fold(0., f64::max);
+    let kinetic_energy: f64 = speeds.clone().map(|speed| 0.5 * speed * speed).sum();
+    let mean_speed = if particles.is_empty() { 0. } else { speeds.sum::<f64>() / particles.len() as f64 };
+
+    writeln!(
+        writer,
+        "{step},{},{},{mean_speed},{max_speed},{kinetic_energy},{step_seconds}",
+        simulation.time(),
+        particles.len(),
+    )
+}
+
+fn write_frame(options: &Options, frame: usize, simulation: &Simulation) -> io::Result<()> {
+    let out = &options.out;
+
+    match options.format {
+        Format::Export(format) => {
+            let path = FrameExporter::new(out, format).frame_path(frame);
+            write_particles_file(path, simulation, format)
+        }
+        Format::Json => {
+            let writer = BufWriter::new(File::create(out.join(format!("frame_{frame:05}.json")))?);
+            serde_json::to_writer(writer, simulation.particles()).map_err(io::Error::from)
+        }
+        Format::Snapshot => simulation.snapshot().write_file(out.join(format!("frame_{frame:05}.bin"))),
+        Format::Png => {
+            // particle radii are in pixels, so they're scaled along with the frame
+            let scale = options.render.scale;
+            let render_options = options.render.with_particle_style(match options.render.particle_style {
+                ParticleStyle::Discs { radius } => ParticleStyle::Discs { radius: radius * scale },
+                ParticleStyle::Metaballs { radius, threshold } => ParticleStyle::Metaballs { radius: radius * scale, threshold },
+            });
+
+            render(simulation, &render_options).write_png_file(out.join(format!("frame_{frame:05}.png")))
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use crate::Float;
+
+/// What happens to particles that reach the simulation boundaries along one axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BoundaryMode {
+    /// Particles are kept inside the boundaries and bounce off them.
+    #[default]
+    Clamp,
+    /// Particles leaving one side come back in from the opposite side, and
+    /// interact with the particles near the opposite side.
+    Periodic,
+    /// Particles leaving the boundaries are removed from the simulation.
+    Open,
+}
+
+impl BoundaryMode {
+    /// Given the difference `diff` between two coordinates on this axis,
+    /// returns the difference to the nearest periodic image.
+    /// Non-periodic modes return `diff` unchanged.
+    pub fn wrap_diff<T: Float>(self, diff: T, extent: T) -> T {
+        if self != BoundaryMode::Periodic || extent <= T::ZERO {
+            return diff;
+        }
+
+        let half = extent * T::from_f32(0.5);
+
+        if diff > half {
+            diff - extent
+        } else if diff < -half {
+            diff + extent
+        } else {
+            diff
+        }
+    }
+    /// Applies this mode to a particle's coordinate `pos` and velocity `vel`
+    /// along one axis, with the boundaries at `min` and `max`.
+    pub(crate) fn apply<T: Float>(self, pos: &mut T, vel: &mut T, min: T, max: T) {
+        match self {
+            BoundaryMode::Clamp => {
+                if *pos < min {
+                    *pos = min;
+                    *vel *= T::from_f32(-0.5);
+                }
+
+                if *pos > max {
+                    *pos = max;
+                    *vel *= T::from_f32(-0.5);
+                }
+            }
+            BoundaryMode::Periodic => {
+                if *pos < min { *pos += max - min; }
+                if *pos > max { *pos -= max - min; }
+            }
+            BoundaryMode::Open => (),
+        }
+    }
+
+    /// Whether a particle at `pos` has left through an open boundary.
+    pub(crate) fn escaped<T: Float>(self, pos: T, min: T, max: T) -> bool {
+        self == BoundaryMode::Open && (pos < min || pos > max)
+    }
+
+    // returns the cell keys next to `key` along one axis, including `key`.
+    // on periodic axes, keys past the boundaries are replaced by the cells
+    // covering the same area on the opposite side.
+    pub(crate) fn neighbor_cell_keys<T: Float>(self, key: i32, min: T, max: T, cell_size: T) -> Vec<i32> {
+        if self != BoundaryMode::Periodic {
+            return vec![key - 1, key, key + 1];
+        }
+
+        let extent = max - min;
+        let to_key = |pos: T| (pos / cell_size).floor().to_f64() as i32;
+        let min_key = to_key(min);
+        let max_key = to_key(max);
+
+        let mut keys = vec![];
+        for key in key - 1..=key + 1 {
+            if key >= min_key && key <= max_key {
+                keys.push(key);
+                continue;
+            }
+
+            let start = T::from_f64(key as f64) * cell_size;
+            let wrapped = if key < min_key { start + extent } else { start - extent };
+
+            // the domain might not be a whole number of cells wide, so the
+            // wrapped area can overlap two cells
+            for key in [to_key(wrapped), to_key(wrapped) + 1] {
+                if key >= min_key && key <= max_key {
+                    keys.push(key);
+                }
+            }
+        }
+
+        keys.sort();
+        keys.dedup();
+        keys
+    }
+}
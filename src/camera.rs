@@ -0,0 +1,93 @@
+//! Maps simulation coordinates to the simulation window, with pan and zoom.
+
+use eframe::egui;
+use egui::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    /// The simulation position at the center of the view.
+    pub center: Pos2,
+    /// Pixels per simulation unit.
+    pub zoom: f32,
+    /// Where the view is on the screen. Updated every frame by `update`.
+    pub viewport: egui::Rect,
+    // whether the next `update` should fit the boundaries into the view
+    fit_pending: bool,
+}
+
+impl Default for Camera {
+    fn default() -> Camera {
+        Camera {
+            center: Pos2::ZERO,
+            zoom: 1.,
+            viewport: egui::Rect::ZERO,
+            fit_pending: true,
+        }
+    }
+}
+
+impl Camera {
+    pub fn world_to_screen(self, pos: Pos2) -> Pos2 {
+        self.viewport.center() + (pos - self.center) * self.zoom
+    }
+
+    pub fn screen_to_world(self, pos: Pos2) -> Pos2 {
+        self.center + (pos - self.viewport.center()) / self.zoom
+    }
+
+    /// The part of the simulation that is in view.
+    pub fn visible_rect(&self) -> egui::Rect {
+        egui::Rect::from_two_pos(self.screen_to_world(self.viewport.min), self.screen_to_world(self.viewport.max))
+    }
+
+    /// Shows `boundaries` as large as fits, on the next `update`.
+    pub fn fit(&mut self) {
+        self.fit_pending = true;
+    }
+
+    pub fn side_panel(&mut self, ui: &mut Ui) {
+        ui.heading("view");
+
+        ui.horizontal(|ui| {
+            ui.add(Slider::new(&mut self.zoom, 0.05..=20.).logarithmic(true).text("zoom"));
+
+            if ui.button("fit").clicked() {
+                self.fit();
+            }
+        });
+        ui.label("right or middle drag to pan, scroll to zoom");
+    }
+
+    /// Called every frame with the simulation window's response, before
+    /// anything is drawn.
+    pub fn update(&mut self, response: &Response, boundaries: fluid_sim::Rect) {
+        self.viewport = response.rect;
+
+        if self.fit_pending && boundaries.width() > 0. && boundaries.height() > 0. {
+            // with a small margin around the boundaries
+            self.zoom = (self.viewport.width() / boundaries.width()).min(self.viewport.height() / boundaries.height()) * 0.95;
+            self.center = pos2(
+                (boundaries.min.x + boundaries.max.x) / 2.,
+                (boundaries.min.y + boundaries.max.y) / 2.,
+            );
+            self.fit_pending = false;
+        }
+
+        if response.dragged_by(PointerButton::Secondary) || response.dragged_by(PointerButton::Middle) {
+            self.center -= response.drag_delta() / self.zoom;
+        }
+
+        if let Some(hover) = response.hover_pos() {
+            let (scroll, pinch) = response.ctx.input(|i| (i.smooth_scroll_delta.y, i.zoom_delta()));
+            let factor = (scroll * 0.002).exp() * pinch;
+
+            if factor != 1. {
+                // keeps the point under the cursor in place
+                let anchor = self.screen_to_world(hover);
+                self.zoom = (self.zoom * factor).clamp(0.05, 20.);
+                self.center = anchor - (hover - self.viewport.center()) / self.zoom;
+            }
+        }
+    }
+}
+
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::*;
+
+/// The indices of the particles in each cell, shared by `Simulation` and
+/// `Simulation3`. Every particle's `cell_index` is its index in its cell.
+#[derive(Debug, Clone)]
+pub(crate) struct CellGrid<K> {
+    cells: HashMap<K, Vec<usize>>,
+}
+
+impl<K> Default for CellGrid<K> {
+    fn default() -> CellGrid<K> {
+        CellGrid { cells: HashMap::new() }
+    }
+}
+
+impl<K: Copy + Eq + Ord + Hash> CellGrid<K> {
+    pub fn get(&self, key: &K) -> Option<&Vec<usize>> {
+        self.cells.get(key)
+    }
+
+    /// Returns the cells ordered by key. The relaxation depends on the order
+    /// cells are visited in, so the hash map's order would make runs differ.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &Vec<usize>)> {
+        let mut cells: Vec<_> = self.cells.iter().collect();
+        cells.sort_by_key(|(key, _)| **key);
+        cells.into_iter()
+    }
+
+    /// Removes every cell.
+    pub fn clear(&mut self) {
+        self.cells.clear();
+    }
+
+    /// Empties every cell, keeping the cells themselves.
+    pub fn empty(&mut self) {
+        for cell in self.cells.values_mut() {
+            cell.clear();
+        }
+    }
+
+    /// Returns the particles in the cells of `keys`, cell by cell.
+    pub fn neighbors(&self, keys: &[K]) -> Vec<usize> {
+        let mut res = vec![];
+
+        for key in keys {
+            if let Some(indices) = self.get(key) {
+                res.extend_from_slice(indices);
+            }
+        }
+
+        res
+    }
+
+    // make sure not to add the same particle multiple times
+    pub fn add<T: Float, V: Vector<T>>(&mut self, particles: &mut [Particle<T, V>], index: usize, key: K) {
+        match self.cells.get_mut(&key) {
+            Some(cell) => {
+                particles[index].cell_index = cell.len();
+                cell.push(index);
+            }
+            None => {
+                particles[index].cell_index = 0;
+                self.cells.insert(key, vec![index]);
+            }
+        }
+    }
+
+    pub fn remove<T: Float, V: Vector<T>>(&mut self, particles: &mut [Particle<T, V>], index: usize, key: K) {
+        let cell = self.cells.get_mut(&key).expect("empty cell");
+        particles[cell[cell.len() - 1]].cell_index = particles[index].cell_index;
+        cell.swap_remove(particles[index].cell_index);
+    }
+
+    /// Moves every particle whose `pos` is in another cell than its
+    /// `prev_pos`, which is the cell it was put in.
+    pub fn update<T: Float, V: Vector<T, CellKey = K>>(&mut self, particles: &mut [Particle<T, V>], cell_size: T) {
+        for i in 0..particles.len() {
+            let prev_cell = particles[i].prev_pos.cell_key(cell_size);
+            let curr_cell = particles[i].pos.cell_key(cell_size);
+
+            if prev_cell != curr_cell {
+                if self.cells.contains_key(&prev_cell) { self.remove(particles, i, prev_cell); }
+                self.add(particles, i, curr_cell);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn particles(n: usize) -> Vec<Particle> {
+        vec![Particle::new(Vec2 { x: 0., y: 0. }, Vec2 { x: 0., y: 0. }); n]
+    }
+
+    #[test]
+    fn add() {
+        let mut particles = particles(2);
+        let mut cells = CellGrid::default();
+        cells.add(&mut particles, 0, (0, 0));
+        assert_eq!(*cells.get(&(0, 0)).unwrap(), vec![0]);
+        cells.add(&mut particles, 1, (0, 0));
+        assert_eq!(*cells.get(&(0, 0)).unwrap(), vec![0, 1]);
+    }
+
+    #[test]
+    fn remove() {
+        let mut particles = particles(4);
+        let mut cells = CellGrid::default();
+        for i in 0..4 {
+            cells.add(&mut particles, i, (0, 0));
+        }
+        cells.remove(&mut particles, 1, (0, 0));
+        assert_eq!(*cells.get(&(0, 0)).unwrap(), vec![0, 3, 2]);
+        cells.remove(&mut particles, 3, (0, 0));
+        assert_eq!(*cells.get(&(0, 0)).unwrap(), vec![0, 2]);
+    }
+}
@@ -0,0 +1,93 @@
+use crate::*;
+
+/// What particles are colored by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorField {
+    /// `MATERIAL_PALETTE`, ignoring the colormap.
+    #[default]
+    Material,
+    Speed,
+    Density,
+    Pressure,
+    Age,
+    Vorticity,
+}
+
+impl ColorField {
+    pub const ALL: [ColorField; 6] = [
+        ColorField::Material,
+        ColorField::Speed,
+        ColorField::Density,
+        ColorField::Pressure,
+        ColorField::Age,
+        ColorField::Vorticity,
+    ];
+
+    /// Returns the field's value for every particle.
+    pub fn values<T: Float>(self, simulation: &Simulation<T>) -> Vec<f64> {
+        let particles = simulation.particles();
+
+        match self {
+            ColorField::Material => particles.iter().map(|particle| particle.material as f64).collect(),
+            ColorField::Speed => particles.iter().map(|particle| particle.vel.dist().to_f64()).collect(),
+            ColorField::Density => particles.iter().map(|particle| particle.density.to_f64()).collect(),
+            ColorField::Pressure => particles.iter().map(|particle| particle.pressure.to_f64()).collect(),
+            ColorField::Age => particles.iter().map(|particle| particle.age.to_f64()).collect(),
+            ColorField::Vorticity => simulation.vorticities().into_iter().map(T::to_f64).collect(),
+        }
+    }
+
+    /// Returns every particle's color and the range of values mapped onto
+    /// `colormap`, which is `None` for `ColorField::Material`.
+    pub fn colors<T: Float>(self, simulation: &Simulation<T>, colormap: Colormap) -> (Vec<Color>, Option<(f64, f64)>) {
+        if self == ColorField::Material {
+            let colors = simulation.particles().iter().map(|particle| material_palette_color(particle.material)).collect();
+            return (colors, None);
+        }
+
+        let values = self.values(simulation);
+        let value_range = values
+            .iter()
+            .fold(None, |range: Option<(f64, f64)>, &value| match range {
+                Some((min, max)) => Some((min.min(value), max.max(value))),
+                None => Some((value, value)),
+            })
+            .unwrap_or((0., 0.));
+        let (min, max) = colormap.range(value_range);
+
+        let colors = values
+            .iter()
+            .map(|&value| colormap.sample(if max > min { (value - min) / (max - min) } else { 0.5 }))
+            .collect();
+
+        (colors, Some((min, max)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rigid_rotation_vorticity() {
+        // a disc of particles turning at 2 radians per second
+        let particles = (0..60)
+            .map(|i| {
+                let angle = i as f32 * 2.399_963;
+                let pos = Vec2 { x: angle.cos(), y: angle.sin() } * (i as f32).sqrt() * 3.;
+                Particle::new(Vec2 { x: 100., y: 100. } + pos, Vec2 { x: -pos.y, y: pos.x } * 2.)
+            })
+            .collect();
+        let mut simulation = Simulation::with_particles(particles);
+        simulation.init();
+
+        // twice the angular velocity
+        for vorticity in ColorField::Vorticity.values(&simulation) {
+            assert!((vorticity - 4.).abs() < 1e-3);
+        }
+
+        let (colors, range) = ColorField::Speed.colors(&simulation, Colormap::Viridis);
+        assert_eq!(colors[0], Colormap::Viridis.sample(0.));
+        assert_eq!(range.unwrap().0, 0.);
+    }
+}
@@ -0,0 +1,100 @@
+use crate::Color;
+
+/// Maps values from 0 to 1 to colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Colormap {
+    /// Dark blue to yellow, perceptually uniform.
+    #[default]
+    Viridis,
+    /// Dark blue through purple to yellow, perceptually uniform.
+    Plasma,
+    /// Blue to white to red, for values centered on 0.
+    Diverging,
+}
+
+const VIRIDIS: [[u8; 3]; 10] = [
+    [68, 1, 84],
+    [72, 40, 120],
+    [62, 73, 137],
+    [49, 104, 142],
+    [38, 130, 142],
+    [31, 158, 137],
+    [53, 183, 121],
+    [110, 206, 88],
+    [181, 222, 43],
+    [253, 231, 37],
+];
+
+const PLASMA: [[u8; 3]; 10] = [
+    [13, 8, 135],
+    [70, 3, 159],
+    [114, 1, 168],
+    [156, 23, 158],
+    [189, 55, 134],
+    [216, 87, 107],
+    [237, 121, 83],
+    [251, 159, 58],
+    [253, 202, 38],
+    [240, 249, 33],
+];
+
+const DIVERGING: [[u8; 3]; 7] = [
+    [59, 76, 192],
+    [115, 150, 245],
+    [176, 203, 252],
+    [221, 221, 221],
+    [246, 191, 166],
+    [234, 123, 96],
+    [180, 4, 38],
+];
+
+impl Colormap {
+    pub const ALL: [Colormap; 3] = [Colormap::Viridis, Colormap::Plasma, Colormap::Diverging];
+
+    /// Returns the color at `t`, which is clamped to 0..=1.
+    pub fn sample(self, t: f64) -> Color {
+        let stops: &[[u8; 3]] = match self {
+            Colormap::Viridis => &VIRIDIS,
+            Colormap::Plasma => &PLASMA,
+            Colormap::Diverging => &DIVERGING,
+        };
+
+        let t = if t.is_nan() { 0. } else { t.clamp(0., 1.) };
+        let scaled = t * (stops.len() - 1) as f64;
+        let i = (scaled.floor() as usize).min(stops.len() - 2);
+        let fract = scaled - i as f64;
+
+        let mut color = [0, 0, 0, 255];
+        for c in 0..3 {
+            let (from, to) = (stops[i][c] as f64, stops[i + 1][c] as f64);
+            color[c] = (from + (to - from) * fract).round() as u8;
+        }
+        color
+    }
+
+    /// Returns the range `value_range` should be mapped from: diverging
+    /// colormaps are centered on 0, the others use `value_range` as is.
+    pub fn range(self, value_range: (f64, f64)) -> (f64, f64) {
+        match self {
+            Colormap::Diverging => {
+                let extent = value_range.0.abs().max(value_range.1.abs());
+                (-extent, extent)
+            }
+            _ => value_range,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample() {
+        assert_eq!(Colormap::Viridis.sample(0.), [68, 1, 84, 255]);
+        assert_eq!(Colormap::Viridis.sample(1.), [253, 231, 37, 255]);
+        assert_eq!(Colormap::Plasma.sample(2.), Colormap::Plasma.sample(1.));
+        assert_eq!(Colormap::Diverging.sample(0.5), [221, 221, 221, 255]);
+        assert_eq!(Colormap::Diverging.range((-1., 3.)), (-3., 3.));
+    }
+}
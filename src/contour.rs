@@ -0,0 +1,209 @@
+//! Iso-contours of sampled fields, e.g. the outline of the fluid from
+//! `Simulation::sample_density`.
+
+use std::collections::HashMap;
+
+use crate::*;
+
+/// A closed polygon. The first point isn't repeated at the end.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contour {
+    /// Ordered so the area above the iso value is on the left, i.e.
+    /// counterclockwise around it when y points up.
+    pub points: Vec<Vec2<f64>>,
+}
+
+impl Contour {
+    /// Positive around areas above the iso value, negative around holes, so
+    /// the signed areas of all contours add up to the area above it.
+    pub fn signed_area(&self) -> f64 {
+        let n = self.points.len();
+
+        (0..n)
+            .map(|i| {
+                let (a, b) = (self.points[i], self.points[(i + 1) % n]);
+                a.x * b.y - b.x * a.y
+            })
+            .sum::<f64>()
+            / 2.
+    }
+
+    /// The smallest rect containing the contour, e.g. for the height of a
+    /// free surface.
+    pub fn bounds(&self) -> Rect<f64> {
+        let mut bounds = Rect {
+            min: Vec2 { x: f64::INFINITY, y: f64::INFINITY },
+            max: Vec2 { x: f64::NEG_INFINITY, y: f64::NEG_INFINITY },
+        };
+
+        for point in &self.points {
+            bounds.min = Vec2 { x: bounds.min.x.min(point.x), y: bounds.min.y.min(point.y) };
+            bounds.max = Vec2 { x: bounds.max.x.max(point.x), y: bounds.max.y.max(point.y) };
+        }
+
+        bounds
+    }
+
+    pub fn perimeter(&self) -> f64 {
+        let n = self.points.len();
+        (0..n).map(|i| Vec2::dist(self.points[(i + 1) % n] - self.points[i])).sum()
+    }
+}
+
+// a grid edge, as the two nodes it connects. nodes outside the grid
+// (index -1 or width/height) are below every iso value, which closes
+// contours along the grid's edges.
+type EdgeKey = ((i64, i64), (i64, i64));
+
+impl Grid<f64> {
+    /// Extracts the contours where the values cross `iso` with marching squares.
+    pub fn contours(&self, iso: f64) -> Vec<Contour> {
+        let value = |x: i64, y: i64| {
+            if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+                f64::NEG_INFINITY
+            } else {
+                self.get(x as usize, y as usize)
+            }
+        };
+
+        // where the contour crosses an edge
+        let crossing = |(a, b): EdgeKey| {
+            let (value_a, value_b) = (value(a.0, a.1), value(b.0, b.1));
+
+            // nodes outside the grid have no position, so the crossing is
+            // at the node inside
+            if value_a == f64::NEG_INFINITY {
+                return self.node_pos(b.0 as usize, b.1 as usize);
+            }
+            if value_b == f64::NEG_INFINITY {
+                return self.node_pos(a.0 as usize, a.1 as usize);
+            }
+
+            let t = (iso - value_a) / (value_b - value_a);
+            let (pos_a, pos_b) = (self.node_pos(a.0 as usize, a.1 as usize), self.node_pos(b.0 as usize, b.1 as usize));
+            pos_a + (pos_b - pos_a) * t
+        };
+
+        // every segment, keyed by the edge it starts on
+        let mut segments: HashMap<EdgeKey, EdgeKey> = HashMap::new();
+
+        for y in -1..self.height as i64 {
+            for x in -1..self.width as i64 {
+                // counterclockwise, starting at the cell's min corner
+                let corners = [(x, y), (x + 1, y), (x + 1, y + 1), (x, y + 1)];
+                let values = corners.map(|(x, y)| value(x, y));
+                let inside = values.map(|value| value >= iso);
+
+                // edge `i` goes from corner `i` to the next one
+                let edge = |i: usize| (corners[i % 4], corners[(i + 1) % 4]);
+                let leaving = |i: usize| inside[i % 4] && !inside[(i + 1) % 4];
+                let entering = |i: usize| !inside[i % 4] && inside[(i + 1) % 4];
+
+                // in the two saddle cases, the diagonal corners above `iso`
+                // are joined if the cell's center is above it too
+                let finite: Vec<f64> = values.into_iter().filter(|value| value.is_finite()).collect();
+                let center_inside = !finite.is_empty() && finite.iter().sum::<f64>() / finite.len() as f64 >= iso;
+
+                for i in 0..4 {
+                    if !leaving(i) {
+                        continue;
+                    }
+
+                    // a segment leaves the area above `iso` on edge `i` and
+                    // goes to the edge where it's entered again
+                    let end = if center_inside {
+                        (i + 1..i + 4).find(|&j| entering(j))
+                    } else {
+                        (i + 1..i + 4).rev().find(|&j| entering(j))
+                    };
+
+                    if let Some(end) = end {
+                        segments.insert(normalize(edge(i)), normalize(edge(end)));
+                    }
+                }
+            }
+        }
+
+        let mut contours = vec![];
+
+        while let Some(&start) = segments.keys().next() {
+            let mut points = vec![];
+            let mut edge = start;
+
+            while let Some(next) = segments.remove(&edge) {
+                points.push(crossing(edge));
+                edge = next;
+            }
+
+            contours.push(Contour { points });
+        }
+
+        contours
+    }
+}
+
+// edges are shared by two cells, which walk them in opposite directions.
+fn normalize((a, b): EdgeKey) -> EdgeKey {
+    if a <= b { (a, b) } else { (b, a) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(f: impl Fn(Vec2<f64>) -> f64) -> Grid<f64> {
+        let rect = Rect { min: Vec2 { x: 0., y: 0. }, max: Vec2 { x: 100., y: 100. } };
+        let mut grid = Grid::new(rect, 101, 101, 0.);
+
+        for y in 0..grid.height {
+            for x in 0..grid.width {
+                grid.set(x, y, f(grid.node_pos(x, y)));
+            }
+        }
+        grid
+    }
+
+    #[test]
+    fn circle() {
+        let grid = grid(|pos| 30. - Vec2::dist(pos - Vec2 { x: 50., y: 50. }));
+        let contours = grid.contours(0.);
+
+        assert_eq!(contours.len(), 1);
+        assert!((contours[0].signed_area() - std::f64::consts::PI * 900.).abs() < 10.);
+        assert!((contours[0].perimeter() - std::f64::consts::PI * 60.).abs() < 1.);
+    }
+
+    #[test]
+    fn fluid_outline() {
+        // a 10 by 5 block of particles, 5 apart
+        let particles = (0..50)
+            .map(|i| Particle::new(Vec2 { x: 100. + (i % 10) as f32 * 5., y: 100. + (i / 10) as f32 * 5. }, Vec2 { x: 0., y: 0. }))
+            .collect();
+        let simulation = Simulation::with_particles(particles);
+
+        let rect = Rect { min: Vec2 { x: 0., y: 0. }, max: Vec2 { x: 250., y: 250. } };
+        let contours = simulation.sample_density(rect, (126, 126)).contours(5.);
+
+        assert_eq!(contours.len(), 1);
+        let bounds = contours[0].bounds();
+        assert!(bounds.min.x > 60. && bounds.max.x < 185.);
+        assert!(bounds.min.y > 60. && bounds.max.y < 160.);
+    }
+
+    #[test]
+    fn ring_and_edges() {
+        // a ring has an outline and a hole
+        let ring = grid(|pos| 10. - (Vec2::dist(pos - Vec2 { x: 50., y: 50. }) - 30.).abs());
+        let contours = ring.contours(0.);
+        let area: f64 = contours.iter().map(Contour::signed_area).sum();
+
+        assert_eq!(contours.len(), 2);
+        assert!((area - std::f64::consts::PI * (1600. - 400.)).abs() < 10.);
+
+        // contours touching the grid's edges are closed along them
+        let contours = grid(|pos| 20. - pos.y).contours(0.);
+
+        assert_eq!(contours.len(), 1);
+        assert!((contours[0].signed_area() - 2000.).abs() < 1e-6);
+    }
+}
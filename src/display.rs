@@ -0,0 +1,276 @@
+//! How the simulation window draws particles.
+
+use eframe::egui;
+use egui::*;
+
+use fluid_sim::{ColorField, Colormap, Image, Simulation};
+
+use crate::{particle_radius, Camera};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DisplayStyle {
+    #[default]
+    Dots,
+    /// A continuous fluid surface, see `fluid_sim::ParticleStyle::Metaballs`.
+    Surface,
+}
+
+/// Where velocity arrows are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VelocityArrows {
+    #[default]
+    Off,
+    Particles,
+    /// At the nodes of a grid the velocity is sampled onto.
+    Grid,
+}
+
+#[derive(Clone)]
+pub struct Display {
+    pub color_field: ColorField,
+    pub colormap: Colormap,
+    pub style: DisplayStyle,
+    /// How far each particle's contribution to the surface reaches, in
+    /// simulation units.
+    pub surface_radius: f32,
+    /// The surface is drawn where the field is above `surface_threshold`.
+    pub surface_threshold: f32,
+    /// How soft the surface's edge is, from 0 (sharp) to 1.
+    pub surface_blur: f32,
+    pub velocity_arrows: VelocityArrows,
+    /// Seconds of movement an arrow is long.
+    pub arrow_scale: f32,
+    pub streamlines: bool,
+    /// Simulation units between the nodes of the arrow grid, and between
+    /// streamline seeds.
+    pub overlay_spacing: f32,
+    // the last surface image, reused by egui until it's replaced
+    surface_texture: Option<TextureHandle>,
+}
+
+// the surface is computed at a lower resolution than the screen
+const SURFACE_PIXEL_SIZE: f32 = 2.;
+
+impl Default for Display {
+    fn default() -> Display {
+        Display {
+            color_field: ColorField::default(),
+            colormap: Colormap::default(),
+            style: DisplayStyle::default(),
+            surface_radius: 12.,
+            surface_threshold: 0.6,
+            surface_blur: 0.1,
+            velocity_arrows: VelocityArrows::default(),
+            arrow_scale: 0.05,
+            streamlines: false,
+            overlay_spacing: 30.,
+            surface_texture: None,
+        }
+    }
+}
+
+impl Display {
+    pub fn side_panel(&mut self, ui: &mut Ui) {
+        ui.heading("display");
+
+        ComboBox::from_label("style")
+            .selected_text(format!("{:?}", self.style))
+            .show_ui(ui, |ui| {
+                for option in [DisplayStyle::Dots, DisplayStyle::Surface] {
+                    ui.selectable_value(&mut self.style, option, format!("{option:?}"));
+                }
+            });
+
+        if self.style == DisplayStyle::Surface {
+            ui.add(Slider::new(&mut self.surface_radius, 4.0..=40.).text("surface radius"));
+            ui.add(Slider::new(&mut self.surface_threshold, 0.05..=3.).text("surface threshold"));
+            ui.add(Slider::new(&mut self.surface_blur, 0.0..=1.).text("surface blur"));
+        }
+
+        ComboBox::from_label("color by")
+            .selected_text(format!("{:?}", self.color_field))
+            .show_ui(ui, |ui| {
+                for option in ColorField::ALL {
+                    ui.selectable_value(&mut self.color_field, option, format!("{option:?}"));
+                }
+            });
+
+        ui.add_enabled_ui(self.color_field != ColorField::Material, |ui| {
+            ComboBox::from_label("colormap")
+                .selected_text(format!("{:?}", self.colormap))
+                .show_ui(ui, |ui| {
+                    for option in Colormap::ALL {
+                        ui.selectable_value(&mut self.colormap, option, format!("{option:?}"));
+                    }
+                });
+        });
+
+        ComboBox::from_label("velocity arrows")
+            .selected_text(format!("{:?}", self.velocity_arrows))
+            .show_ui(ui, |ui| {
+                for option in [VelocityArrows::Off, VelocityArrows::Particles, VelocityArrows::Grid] {
+                    ui.selectable_value(&mut self.velocity_arrows, option, format!("{option:?}"));
+                }
+            });
+        ui.add_enabled(
+            self.velocity_arrows != VelocityArrows::Off,
+            Slider::new(&mut self.arrow_scale, 0.005..=0.5).logarithmic(true).text("arrow scale"),
+        );
+        ui.checkbox(&mut self.streamlines, "streamlines");
+        ui.add_enabled(
+            self.velocity_arrows == VelocityArrows::Grid || self.streamlines,
+            Slider::new(&mut self.overlay_spacing, 10.0..=100.).text("overlay spacing"),
+        );
+    }
+
+    /// Draws the particles, and a legend in the top right corner of the view.
+    pub fn draw(&mut self, painter: &Painter, camera: &Camera, simulation: &Simulation) {
+        let (colors, range) = self.color_field.colors(simulation, self.colormap);
+
+        match self.style {
+            DisplayStyle::Dots => {
+                let radius = particle_radius(camera);
+
+                for (particle, &color) in simulation.particles().iter().zip(&colors) {
+                    painter.circle_filled(camera.world_to_screen(pos2(particle.pos.x, particle.pos.y)), radius, color32(color));
+                }
+            }
+            DisplayStyle::Surface => self.draw_surface(painter, camera, simulation, &colors),
+        }
+
+        self.draw_overlays(painter, camera, simulation);
+
+        if let Some(range) = range {
+            self.draw_legend(painter, camera.viewport, range);
+        }
+    }
+
+    fn draw_surface(&mut self, painter: &Painter, camera: &Camera, simulation: &Simulation, colors: &[fluid_sim::Color]) {
+        let rect = camera.viewport;
+        let width = (rect.width() / SURFACE_PIXEL_SIZE).ceil().max(1.) as u32;
+        let height = (rect.height() / SURFACE_PIXEL_SIZE).ceil().max(1.) as u32;
+
+        // particle positions in surface pixels
+        let particles: Vec<_> = simulation
+            .particles()
+            .iter()
+            .zip(colors)
+            .map(|(particle, &color)| {
+                let pos = (camera.world_to_screen(pos2(particle.pos.x, particle.pos.y)) - rect.min) / SURFACE_PIXEL_SIZE;
+                (fluid_sim::Vec2 { x: pos.x as f64, y: pos.y as f64 }, color)
+            })
+            .collect();
+
+        let mut image = Image::new(width, height, [0, 0, 0, 0]);
+        fluid_sim::draw_metaballs(
+            &mut image,
+            &particles,
+            (self.surface_radius * camera.zoom / SURFACE_PIXEL_SIZE) as f64,
+            self.surface_threshold as f64,
+            self.surface_blur as f64,
+        );
+
+        let image = ColorImage::from_rgba_unmultiplied([width as usize, height as usize], image.pixels.as_flattened());
+        let texture = match &mut self.surface_texture {
+            Some(texture) => {
+                texture.set(image, TextureOptions::LINEAR);
+                texture
+            }
+            None => self.surface_texture.insert(painter.ctx().load_texture("surface", image, TextureOptions::LINEAR)),
+        };
+
+        let size = vec2(width as f32, height as f32) * SURFACE_PIXEL_SIZE;
+        painter.image(
+            texture.id(),
+            egui::Rect::from_min_size(rect.min, size),
+            egui::Rect::from_min_max(pos2(0., 0.), pos2(1., 1.)),
+            Color32::WHITE,
+        );
+    }
+
+    fn draw_overlays(&self, painter: &Painter, camera: &Camera, simulation: &Simulation) {
+        let arrow_stroke = Stroke::new(1., Color32::WHITE);
+        let to_screen_f64 = |pos: fluid_sim::Vec2<f64>| camera.world_to_screen(pos2(pos.x as f32, pos.y as f32));
+        // pixels per unit of velocity
+        let arrow_scale = self.arrow_scale * camera.zoom;
+
+        if self.velocity_arrows == VelocityArrows::Particles {
+            for particle in simulation.particles() {
+                let origin = camera.world_to_screen(pos2(particle.pos.x, particle.pos.y));
+                painter.arrow(origin, vec2(particle.vel.x, particle.vel.y) * arrow_scale, arrow_stroke);
+            }
+        }
+
+        if self.velocity_arrows != VelocityArrows::Grid && !self.streamlines {
+            return;
+        }
+
+        let boundaries = simulation.boundaries;
+        let resolution = (
+            (boundaries.width() / self.overlay_spacing).ceil() as usize + 1,
+            (boundaries.height() / self.overlay_spacing).ceil() as usize + 1,
+        );
+        let velocities = simulation.sample_velocity(boundaries, resolution);
+
+        if self.velocity_arrows == VelocityArrows::Grid {
+            for y in 0..velocities.height {
+                for x in 0..velocities.width {
+                    let velocity = velocities.get(x, y);
+                    let origin = to_screen_f64(velocities.node_pos(x, y));
+                    painter.arrow(origin, vec2(velocity.x as f32, velocity.y as f32) * arrow_scale, arrow_stroke);
+                }
+            }
+        }
+
+        if self.streamlines {
+            let stroke = Stroke::new(1., Color32::from_white_alpha(140));
+            let step_length = self.overlay_spacing as f64 / 4.;
+
+            // seeded between the arrows, if both are shown
+            for y in 0..velocities.height - 1 {
+                for x in 0..velocities.width - 1 {
+                    let seed = velocities.node_pos(x, y) + velocities.spacing() * 0.5;
+                    let points = velocities.streamline(seed, step_length, 40);
+
+                    if points.len() > 1 {
+                        painter.add(Shape::line(points.into_iter().map(to_screen_f64).collect(), stroke));
+                    }
+                }
+            }
+        }
+    }
+
+    fn draw_legend(&self, painter: &Painter, rect: egui::Rect, (min, max): (f64, f64)) {
+        const STEPS: usize = 32;
+
+        let bar = egui::Rect::from_min_size(pos2(rect.max.x - 170., rect.min.y + 28.), vec2(160., 12.));
+        let step_width = bar.width() / STEPS as f32;
+
+        for i in 0..STEPS {
+            let min_x = bar.min.x + i as f32 * step_width;
+            painter.rect_filled(
+                egui::Rect::from_min_max(pos2(min_x, bar.min.y), pos2(min_x + step_width, bar.max.y)),
+                0.,
+                color32(self.colormap.sample((i as f64 + 0.5) / STEPS as f64)),
+            );
+        }
+        painter.rect_stroke(bar, 0., Stroke::new(1., Color32::WHITE), StrokeKind::Outside);
+
+        let font = FontId::proportional(12.);
+        painter.text(pos2(bar.center().x, bar.min.y - 4.), Align2::CENTER_BOTTOM, format!("{:?}", self.color_field), font.clone(), Color32::WHITE);
+        painter.text(pos2(bar.min.x, bar.max.y + 2.), Align2::LEFT_TOP, format_value(min), font.clone(), Color32::WHITE);
+        painter.text(pos2(bar.max.x, bar.max.y + 2.), Align2::RIGHT_TOP, format_value(max), font, Color32::WHITE);
+    }
+}
+
+pub fn color32([r, g, b, a]: fluid_sim::Color) -> Color32 {
+    Color32::from_rgba_unmultiplied(r, g, b, a)
+}
+
+fn format_value(value: f64) -> String {
+    if value != 0. && !(0.01..10000.).contains(&value.abs()) {
+        format!("{value:.2e}")
+    } else {
+        format!("{value:.2}")
+    }
+}
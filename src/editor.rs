@@ -0,0 +1,372 @@
+//! The scenario editor shown before the simulation starts.
+
+use std::collections::HashMap;
+
+use eframe::egui;
+use egui::*;
+
+use fluid_sim::{Emitter, Obstacle, Particle, SimulationBuilder};
+
+use crate::Camera;
+
+/// The most lattice points a single fill can have, so a large shape at a
+/// small spacing doesn't freeze the editor.
+pub const MAX_FILL_POINTS: usize = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditorTool {
+    /// Paints particles under the cursor.
+    Brush,
+    /// Fills a dragged rectangle with particles.
+    Rectangle,
+    /// Fills a dragged circle with particles.
+    Circle,
+    /// Places an obstacle with the chosen shape.
+    Obstacle,
+    /// Places an emitter with a click.
+    Emitter,
+    /// Removes particles, obstacles and emitters under the cursor.
+    Erase,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    Rectangle,
+    Circle,
+}
+
+#[derive(Debug, Clone)]
+pub struct Editor {
+    pub tool: EditorTool,
+    pub particles: Vec<Particle>,
+    pub obstacles: Vec<Obstacle>,
+    pub emitters: Vec<Emitter>,
+    /// Distance between particles placed by the brush and shapes.
+    pub spacing: f32,
+    pub brush_radius: f32,
+    pub material: u32,
+    /// Initial velocity of placed particles and emitters.
+    pub velocity: egui::Vec2,
+    pub obstacle_shape: Shape,
+    pub emitter_rate: f32,
+    pub emitter_width: f32,
+    // where the current drag started, in simulation coordinates
+    drag_start: Option<Pos2>,
+}
+
+impl Default for Editor {
+    fn default() -> Editor {
+        let mut editor = Editor {
+            tool: EditorTool::Brush,
+            particles: vec![],
+            obstacles: vec![],
+            emitters: vec![],
+            spacing: 7.,
+            brush_radius: 30.,
+            material: 0,
+            velocity: egui::Vec2::ZERO,
+            obstacle_shape: Shape::Rectangle,
+            emitter_rate: 60.,
+            emitter_width: 20.,
+            drag_start: None,
+        };
+
+        // roughly the block of particles the viewer used to start with
+        editor.fill(
+            egui::Rect::from_min_max(pos2(17., 17.), pos2(122., 136.)),
+            |_| true,
+        );
+
+        editor
+    }
+}
+
+impl Editor {
+    pub fn side_panel(&mut self, ui: &mut Ui) {
+        ui.heading("editor");
+
+        ComboBox::from_label("editor tool")
+            .selected_text(format!("{:?}", self.tool))
+            .show_ui(ui, |ui| {
+                for option in [
+                    EditorTool::Brush,
+                    EditorTool::Rectangle,
+                    EditorTool::Circle,
+                    EditorTool::Obstacle,
+                    EditorTool::Emitter,
+                    EditorTool::Erase,
+                ] {
+                    ui.selectable_value(&mut self.tool, option, format!("{option:?}"));
+                }
+            });
+
+        ui.add(Slider::new(&mut self.spacing, 2.0..=30.).text("spacing"));
+        ui.add(Slider::new(&mut self.brush_radius, 5.0..=200.).text("brush radius"));
+        ui.add(Slider::new(&mut self.material, 0..=7).text("material"));
+        ui.add(Slider::new(&mut self.velocity.x, -500.0..=500.).text("velocity x"));
+        ui.add(Slider::new(&mut self.velocity.y, -500.0..=500.).text("velocity y"));
+
+        ComboBox::from_label("obstacle shape")
+            .selected_text(format!("{:?}", self.obstacle_shape))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.obstacle_shape, Shape::Rectangle, "Rectangle");
+                ui.selectable_value(&mut self.obstacle_shape, Shape::Circle, "Circle");
+            });
+
+        ui.add(Slider::new(&mut self.emitter_rate, 1.0..=600.).text("emitter rate"));
+        ui.add(Slider::new(&mut self.emitter_width, 0.0..=100.).text("emitter width"));
+
+        ui.label(format!(
+            "{} particles, {} obstacles, {} emitters",
+            self.particles.len(),
+            self.obstacles.len(),
+            self.emitters.len(),
+        ));
+
+        if ui.button("clear").clicked() {
+            self.particles.clear();
+            self.obstacles.clear();
+            self.emitters.clear();
+        }
+    }
+
+    /// Returns `builder` with the editor's particles, obstacles and emitters.
+    pub fn with_builder(&self, builder: &SimulationBuilder) -> SimulationBuilder {
+        SimulationBuilder {
+            particles: self.particles.clone(),
+            obstacles: self.obstacles.clone(),
+            emitters: self.emitters.clone(),
+            ..builder.clone()
+        }
+    }
+
+    /// Replaces the editor's particles, obstacles and emitters with the ones in `builder`.
+    pub fn load(&mut self, builder: &SimulationBuilder) {
+        self.particles = builder.particles.clone();
+        self.obstacles = builder.obstacles.clone();
+        self.emitters = builder.emitters.clone();
+    }
+
+    pub fn handle_input(&mut self, response: &Response, camera: &Camera) {
+        // the other buttons pan the camera
+        let panning = [PointerButton::Secondary, PointerButton::Middle]
+            .into_iter()
+            .any(|button| response.drag_started_by(button) || response.dragged_by(button) || response.drag_stopped_by(button));
+
+        if panning {
+            return;
+        }
+
+        let Some(pointer) = response.interact_pointer_pos().map(|pos| camera.screen_to_world(pos)) else {
+            return;
+        };
+
+        if response.drag_started() || (response.clicked() && self.drag_start.is_none()) {
+            self.drag_start = Some(pointer);
+        }
+
+        let down = response.is_pointer_button_down_on();
+        let released = response.drag_stopped() || response.clicked();
+
+        match self.tool {
+            EditorTool::Brush if down || released => {
+                let radius = self.brush_radius;
+                self.fill(
+                    egui::Rect::from_center_size(pointer, egui::Vec2::splat(radius * 2.)),
+                    |pos| pos.distance(pointer) <= radius,
+                );
+            }
+            EditorTool::Erase if down || released => {
+                let radius = self.brush_radius;
+                let center = fluid_sim::Vec2 { x: pointer.x, y: pointer.y };
+
+                self.particles.retain(|particle| pos2(particle.pos.x, particle.pos.y).distance(pointer) > radius);
+                self.obstacles.retain(|obstacle| !obstacle.contains(center));
+                self.emitters.retain(|emitter| pos2(emitter.pos.x, emitter.pos.y).distance(pointer) > radius);
+            }
+            EditorTool::Rectangle if released => {
+                if let Some(start) = self.drag_start {
+                    self.fill(egui::Rect::from_two_pos(start, pointer), |_| true);
+                }
+            }
+            EditorTool::Circle if released => {
+                if let Some(start) = self.drag_start {
+                    let radius = start.distance(pointer);
+                    self.fill(
+                        egui::Rect::from_center_size(start, egui::Vec2::splat(radius * 2.)),
+                        |pos| pos.distance(start) <= radius,
+                    );
+                }
+            }
+            EditorTool::Obstacle if released => {
+                if let Some(start) = self.drag_start {
+                    self.obstacles.push(shape_obstacle(self.obstacle_shape, start, pointer));
+                }
+            }
+            EditorTool::Emitter if response.clicked() => {
+                self.emitters.push(
+                    Emitter::new(
+                        fluid_sim::Vec2 { x: pointer.x, y: pointer.y },
+                        fluid_sim::Vec2 { x: self.velocity.x, y: self.velocity.y },
+                        self.emitter_rate,
+                        self.emitter_width,
+                    )
+                    .with_material(self.material),
+                );
+            }
+            _ => (),
+        }
+
+        if released {
+            self.drag_start = None;
+        }
+    }
+
+    pub fn draw(&self, painter: &Painter, response: &Response, camera: &Camera) {
+        for particle in &self.particles {
+            painter.circle_filled(
+                camera.world_to_screen(pos2(particle.pos.x, particle.pos.y)),
+                particle_radius(camera),
+                material_color(particle.material),
+            );
+        }
+
+        draw_obstacles(painter, &self.obstacles, camera);
+        draw_emitters(painter, &self.emitters, camera);
+
+        let stroke = Stroke::new(1., Color32::WHITE);
+
+        // preview of the shape being dragged
+        if let (Some(start), Some(pointer)) = (self.drag_start, response.interact_pointer_pos()) {
+            let start = camera.world_to_screen(start);
+
+            match (self.tool, self.obstacle_shape) {
+                (EditorTool::Rectangle, _) | (EditorTool::Obstacle, Shape::Rectangle) => {
+                    painter.rect_stroke(egui::Rect::from_two_pos(start, pointer), 0., stroke, StrokeKind::Middle);
+                }
+                (EditorTool::Circle, _) | (EditorTool::Obstacle, Shape::Circle) => {
+                    painter.circle_stroke(start, start.distance(pointer), stroke);
+                }
+                _ => (),
+            }
+        }
+
+        if let (EditorTool::Brush | EditorTool::Erase, Some(hover)) = (self.tool, response.hover_pos()) {
+            painter.circle_stroke(hover, self.brush_radius * camera.zoom, stroke);
+        }
+    }
+
+    // adds particles on a `spacing` sized lattice at every point of `bounds`
+    // where `contains` is true, skipping points that already have a particle.
+    // does nothing if the lattice has more than `MAX_FILL_POINTS` points.
+    fn fill(&mut self, bounds: egui::Rect, contains: impl Fn(Pos2) -> bool) {
+        let spacing = self.spacing;
+        let min_x = (bounds.min.x / spacing).ceil() as i32;
+        let max_x = (bounds.max.x / spacing).floor() as i32;
+        let min_y = (bounds.min.y / spacing).ceil() as i32;
+        let max_y = (bounds.max.y / spacing).floor() as i32;
+
+        let points = (max_x as f64 - min_x as f64 + 1.).max(0.) * (max_y as f64 - min_y as f64 + 1.).max(0.);
+        if points > MAX_FILL_POINTS as f64 {
+            return;
+        }
+
+        // the existing particles in `spacing` sized cells. the ones added
+        // here are a whole `spacing` apart, so they aren't added to it
+        let key = |pos: Pos2| ((pos.x / spacing).floor() as i32, (pos.y / spacing).floor() as i32);
+        let mut cells: HashMap<(i32, i32), Vec<Pos2>> = HashMap::new();
+        for particle in &self.particles {
+            let pos = pos2(particle.pos.x, particle.pos.y);
+            cells.entry(key(pos)).or_default().push(pos);
+        }
+
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                let pos = pos2(x as f32 * spacing, y as f32 * spacing);
+
+                if !contains(pos) {
+                    continue;
+                }
+
+                let (cell_x, cell_y) = key(pos);
+                let occupied = (cell_y - 1..=cell_y + 1)
+                    .flat_map(|y| (cell_x - 1..=cell_x + 1).map(move |x| (x, y)))
+                    .filter_map(|cell| cells.get(&cell))
+                    .flatten()
+                    .any(|particle| particle.distance(pos) < spacing * 0.5);
+
+                if !occupied {
+                    self.particles.push(
+                        Particle::new(
+                            fluid_sim::Vec2 { x: pos.x, y: pos.y },
+                            fluid_sim::Vec2 { x: self.velocity.x, y: self.velocity.y },
+                        )
+                        .with_material(self.material),
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn shape_obstacle(shape: Shape, start: Pos2, end: Pos2) -> Obstacle {
+    match shape {
+        Shape::Rectangle => {
+            let rect = egui::Rect::from_two_pos(start, end);
+
+            Obstacle::Rect(fluid_sim::Rect {
+                min: fluid_sim::Vec2 { x: rect.min.x, y: rect.min.y },
+                max: fluid_sim::Vec2 { x: rect.max.x, y: rect.max.y },
+            })
+        }
+        Shape::Circle => Obstacle::Circle {
+            center: fluid_sim::Vec2 { x: start.x, y: start.y },
+            radius: start.distance(end),
+        },
+    }
+}
+
+pub fn material_color(material: u32) -> Color32 {
+    let [r, g, b, a] = fluid_sim::material_palette_color(material);
+    Color32::from_rgba_unmultiplied(r, g, b, a)
+}
+
+/// Particles are drawn 3 simulation units wide, but at least a pixel.
+pub fn particle_radius(camera: &Camera) -> f32 {
+    (3. * camera.zoom).max(1.)
+}
+
+pub fn draw_obstacles(painter: &Painter, obstacles: &[Obstacle], camera: &Camera) {
+    let fill = Color32::from_gray(90);
+
+    for obstacle in obstacles {
+        match *obstacle {
+            Obstacle::Circle { center, radius } => {
+                painter.circle_filled(camera.world_to_screen(pos2(center.x, center.y)), radius * camera.zoom, fill);
+            }
+            Obstacle::Rect(rect) => {
+                painter.rect_filled(
+                    egui::Rect::from_min_max(
+                        camera.world_to_screen(pos2(rect.min.x, rect.min.y)),
+                        camera.world_to_screen(pos2(rect.max.x, rect.max.y)),
+                    ),
+                    0.,
+                    fill,
+                );
+            }
+        }
+    }
+}
+
+pub fn draw_emitters(painter: &Painter, emitters: &[Emitter], camera: &Camera) {
+    for emitter in emitters {
+        let pos = camera.world_to_screen(pos2(emitter.pos.x, emitter.pos.y));
+        let velocity = egui::vec2(emitter.velocity.x, emitter.velocity.y);
+
+        painter.circle_stroke(pos, 5., Stroke::new(2., material_color(emitter.material)));
+
+        if velocity != egui::Vec2::ZERO {
+            painter.arrow(pos, velocity.normalized() * 20., Stroke::new(2., Color32::WHITE));
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use crate::*;
+
+/// Continuously adds particles to the simulation.
+/// Particles are spawned along a line of `width` centered on `pos`,
+/// perpendicular to `velocity`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Emitter<T = f32> {
+    pub pos: Vec2<T>,
+    /// Initial velocity of the emitted particles.
+    pub velocity: Vec2<T>,
+    /// Particles emitted per second.
+    pub rate: T,
+    pub width: T,
+    pub material: u32,
+    // fraction of a particle left over from previous steps
+    pub(crate) pending: T,
+    pub(crate) emitted: u64,
+}
+
+impl<T: Float> Emitter<T> {
+    pub fn new(pos: Vec2<T>, velocity: Vec2<T>, rate: T, width: T) -> Emitter<T> {
+        Emitter {
+            pos,
+            velocity,
+            rate,
+            width,
+            material: 0,
+            pending: T::ZERO,
+            emitted: 0,
+        }
+    }
+
+    pub fn with_material(&self, material: u32) -> Emitter<T> {
+        Emitter {
+            material,
+            ..*self
+        }
+    }
+
+    /// Returns the particles emitted during a step of `dt` seconds.
+    pub fn emit(&mut self, dt: T) -> Vec<Particle<T>> {
+        self.pending += self.rate * dt;
+
+        let mut particles = vec![];
+        let across = if self.velocity.dist_sqr() == T::ZERO {
+            Vec2 { x: T::ONE, y: T::ZERO }
+        } else {
+            Vec2 { x: -self.velocity.y, y: self.velocity.x }.normalize()
+        };
+
+        while self.pending >= T::ONE {
+            self.pending -= T::ONE;
+
+            // cycle through 5 evenly spaced slots across the emitter
+            let slot = T::from_f64((self.emitted % 5) as f64 / 4. - 0.5);
+            let pos = self.pos + across * (self.width * slot);
+            self.emitted += 1;
+
+            let mut particle = Particle::new(pos, self.velocity);
+            particle.material = self.material;
+            particles.push(particle);
+        }
+
+        particles
+    }
+}
@@ -0,0 +1,295 @@
+//! Writes particle frames for post-processing, e.g. in ParaView or Python.
+//!
+//! Every format has the same fields per particle: position, velocity,
+//! density, pressure and material. Density and pressure are computed from
+//! the current positions with `Simulation::densities`, rather than taken
+//! from `Particle::density`.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// One particle per line, with a header line.
+    Csv,
+    /// Legacy ASCII VTK polydata.
+    Vtk,
+    /// VTK XML unstructured grid.
+    Vtu,
+    /// ASCII PLY point cloud.
+    Ply,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Vtk => "vtk",
+            ExportFormat::Vtu => "vtu",
+            ExportFormat::Ply => "ply",
+        }
+    }
+}
+
+/// Writes one file per frame to `dir`, named `{prefix}_{frame:05}.{extension}`.
+#[derive(Debug, Clone)]
+pub struct FrameExporter {
+    pub dir: PathBuf,
+    pub prefix: String,
+    pub format: ExportFormat,
+    /// The number of the next frame written.
+    pub frame: usize,
+}
+
+impl FrameExporter {
+    pub fn new(dir: impl Into<PathBuf>, format: ExportFormat) -> FrameExporter {
+        FrameExporter {
+            dir: dir.into(),
+            prefix: "frame".to_string(),
+            format,
+            frame: 0,
+        }
+    }
+
+    pub fn with_prefix(&self, prefix: impl Into<String>) -> FrameExporter {
+        FrameExporter {
+            prefix: prefix.into(),
+            ..self.clone()
+        }
+    }
+
+    pub fn frame_path(&self, frame: usize) -> PathBuf {
+        self.dir.join(format!("{}_{frame:05}.{}", self.prefix, self.format.extension()))
+    }
+
+    /// Writes the next frame, creating `dir` if needed, and returns its path.
+    pub fn export<T: Float>(&mut self, simulation: &Simulation<T>) -> io::Result<PathBuf> {
+        fs::create_dir_all(&self.dir)?;
+
+        let path = self.frame_path(self.frame);
+        write_particles_file(&path, simulation, self.format)?;
+        self.frame += 1;
+
+        Ok(path)
+    }
+}
+
+pub fn write_particles_file<T: Float>(path: impl AsRef<Path>, simulation: &Simulation<T>, format: ExportFormat) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_particles(&mut writer, simulation, format)?;
+    writer.flush()
+}
+
+pub fn write_particles<T: Float>(writer: &mut impl Write, simulation: &Simulation<T>, format: ExportFormat) -> io::Result<()> {
+    let particles = exported_particles(simulation);
+
+    match format {
+        ExportFormat::Csv => write_csv(writer, &particles),
+        ExportFormat::Vtk => write_vtk(writer, &particles),
+        ExportFormat::Vtu => write_vtu(writer, &particles),
+        ExportFormat::Ply => write_ply(writer, &particles),
+    }
+}
+
+struct ExportedParticle {
+    pos: Vec2<f64>,
+    vel: Vec2<f64>,
+    density: f64,
+    pressure: f64,
+    material: u32,
+}
+
+fn exported_particles<T: Float>(simulation: &Simulation<T>) -> Vec<ExportedParticle> {
+    simulation
+        .particles()
+        .iter()
+        .zip(simulation.densities())
+        .map(|(particle, (density, _))| ExportedParticle {
+            pos: Vec2 { x: particle.pos.x.to_f64(), y: particle.pos.y.to_f64() },
+            vel: Vec2 { x: particle.vel.x.to_f64(), y: particle.vel.y.to_f64() },
+            density: density.to_f64(),
+            pressure: (simulation.pressure_multiplier * (density - simulation.rest_density)).to_f64(),
+            material: particle.material,
+        })
+        .collect()
+}
+
+fn write_csv(writer: &mut impl Write, particles: &[ExportedParticle]) -> io::Result<()> {
+    writeln!(writer, "x,y,vx,vy,density,pressure,material")?;
+
+    for p in particles {
+        writeln!(writer, "{},{},{},{},{},{},{}", p.pos.x, p.pos.y, p.vel.x, p.vel.y, p.density, p.pressure, p.material)?;
+    }
+
+    Ok(())
+}
+
+fn write_vtk(writer: &mut impl Write, particles: &[ExportedParticle]) -> io::Result<()> {
+    let n = particles.len();
+
+    writeln!(writer, "# vtk DataFile Version 3.0")?;
+    writeln!(writer, "fluid_sim particles")?;
+    writeln!(writer, "ASCII")?;
+    writeln!(writer, "DATASET POLYDATA")?;
+
+    writeln!(writer, "POINTS {n} double")?;
+    for p in particles {
+        writeln!(writer, "{} {} 0", p.pos.x, p.pos.y)?;
+    }
+
+    writeln!(writer, "VERTICES {n} {}", n * 2)?;
+    for i in 0..n {
+        writeln!(writer, "1 {i}")?;
+    }
+
+    writeln!(writer, "POINT_DATA {n}")?;
+
+    writeln!(writer, "VECTORS velocity double")?;
+    for p in particles {
+        writeln!(writer, "{} {} 0", p.vel.x, p.vel.y)?;
+    }
+
+    writeln!(writer, "SCALARS density double 1")?;
+    writeln!(writer, "LOOKUP_TABLE default")?;
+    for p in particles {
+        writeln!(writer, "{}", p.density)?;
+    }
+
+    writeln!(writer, "SCALARS pressure double 1")?;
+    writeln!(writer, "LOOKUP_TABLE default")?;
+    for p in particles {
+        writeln!(writer, "{}", p.pressure)?;
+    }
+
+    writeln!(writer, "SCALARS material int 1")?;
+    writeln!(writer, "LOOKUP_TABLE default")?;
+    for p in particles {
+        writeln!(writer, "{}", p.material)?;
+    }
+
+    Ok(())
+}
+
+fn write_vtu(writer: &mut impl Write, particles: &[ExportedParticle]) -> io::Result<()> {
+    let n = particles.len();
+
+    writeln!(writer, r#"<?xml version="1.0"?>"#)?;
+    writeln!(writer, r#"<VTKFile type="UnstructuredGrid" version="0.1" byte_order="LittleEndian">"#)?;
+    writeln!(writer, "<UnstructuredGrid>")?;
+    writeln!(writer, r#"<Piece NumberOfPoints="{n}" NumberOfCells="{n}">"#)?;
+
+    writeln!(writer, "<Points>")?;
+    writeln!(writer, r#"<DataArray type="Float64" NumberOfComponents="3" format="ascii">"#)?;
+    for p in particles {
+        writeln!(writer, "{} {} 0", p.pos.x, p.pos.y)?;
+    }
+    writeln!(writer, "</DataArray>")?;
+    writeln!(writer, "</Points>")?;
+
+    // one vertex cell per particle
+    writeln!(writer, "<Cells>")?;
+    writeln!(writer, r#"<DataArray type="Int64" Name="connectivity" format="ascii">"#)?;
+    for i in 0..n {
+        writeln!(writer, "{i}")?;
+    }
+    writeln!(writer, "</DataArray>")?;
+    writeln!(writer, r#"<DataArray type="Int64" Name="offsets" format="ascii">"#)?;
+    for i in 0..n {
+        writeln!(writer, "{}", i + 1)?;
+    }
+    writeln!(writer, "</DataArray>")?;
+    writeln!(writer, r#"<DataArray type="UInt8" Name="types" format="ascii">"#)?;
+    for _ in 0..n {
+        writeln!(writer, "1")?;
+    }
+    writeln!(writer, "</DataArray>")?;
+    writeln!(writer, "</Cells>")?;
+
+    writeln!(writer, r#"<PointData Vectors="velocity" Scalars="density">"#)?;
+    writeln!(writer, r#"<DataArray type="Float64" Name="velocity" NumberOfComponents="3" format="ascii">"#)?;
+    for p in particles {
+        writeln!(writer, "{} {} 0", p.vel.x, p.vel.y)?;
+    }
+    writeln!(writer, "</DataArray>")?;
+    writeln!(writer, r#"<DataArray type="Float64" Name="density" format="ascii">"#)?;
+    for p in particles {
+        writeln!(writer, "{}", p.density)?;
+    }
+    writeln!(writer, "</DataArray>")?;
+    writeln!(writer, r#"<DataArray type="Float64" Name="pressure" format="ascii">"#)?;
+    for p in particles {
+        writeln!(writer, "{}", p.pressure)?;
+    }
+    writeln!(writer, "</DataArray>")?;
+    writeln!(writer, r#"<DataArray type="UInt32" Name="material" format="ascii">"#)?;
+    for p in particles {
+        writeln!(writer, "{}", p.material)?;
+    }
+    writeln!(writer, "</DataArray>")?;
+    writeln!(writer, "</PointData>")?;
+
+    writeln!(writer, "</Piece>")?;
+    writeln!(writer, "</UnstructuredGrid>")?;
+    writeln!(writer, "</VTKFile>")
+}
+
+fn write_ply(writer: &mut impl Write, particles: &[ExportedParticle]) -> io::Result<()> {
+    writeln!(writer, "ply")?;
+    writeln!(writer, "format ascii 1.0")?;
+    writeln!(writer, "comment fluid_sim particles")?;
+    writeln!(writer, "element vertex {}", particles.len())?;
+    for property in ["x", "y", "z", "vx", "vy", "density", "pressure"] {
+        writeln!(writer, "property double {property}")?;
+    }
+    writeln!(writer, "property uint material")?;
+    writeln!(writer, "end_header")?;
+
+    for p in particles {
+        writeln!(writer, "{} {} 0 {} {} {} {} {}", p.pos.x, p.pos.y, p.vel.x, p.vel.y, p.density, p.pressure, p.material)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_simulation() -> Simulation {
+        let particles = (0..12)
+            .map(|i| Particle::new(Vec2 { x: 50. + (i % 4) as f32 * 10., y: 50. + (i / 4) as f32 * 10. }, Vec2 { x: 1., y: 2. }))
+            .collect();
+
+        let mut simulation = Simulation::with_particles(particles);
+        simulation.init();
+        simulation
+    }
+
+    #[test]
+    fn csv_and_ply() {
+        let simulation = test_simulation();
+
+        let mut csv = vec![];
+        write_particles(&mut csv, &simulation, ExportFormat::Csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+
+        assert_eq!(csv.lines().count(), 13);
+        assert!(csv.lines().nth(1).unwrap().starts_with("50,50,1,2,"));
+
+        let mut ply = vec![];
+        write_particles(&mut ply, &simulation, ExportFormat::Ply).unwrap();
+        let ply = String::from_utf8(ply).unwrap();
+
+        assert!(ply.contains("element vertex 12\n"));
+        assert_eq!(ply.split("end_header\n").nth(1).unwrap().lines().count(), 12);
+    }
+
+    #[test]
+    fn frame_names() {
+        let exporter = FrameExporter::new("out", ExportFormat::Vtu).with_prefix("dam");
+        assert_eq!(exporter.frame_path(12), Path::new("out").join("dam_00012.vtu"));
+    }
+}
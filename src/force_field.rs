@@ -0,0 +1,202 @@
+use std::fmt::Debug;
+
+use crate::*;
+
+/// An external force applied to every particle at the start of each step.
+pub trait ForceField<T: Float = f32>: Debug + Send + Sync {
+    /// Returns the acceleration of a particle at `pos` moving with `vel`,
+    /// `time` seconds after the simulation started.
+    fn acceleration(&self, pos: Vec2<T>, vel: Vec2<T>, time: T) -> Vec2<T>;
+}
+
+/// The same acceleration everywhere, like gravity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Uniform<T = f32> {
+    pub acceleration: Vec2<T>,
+}
+
+impl<T: Float> ForceField<T> for Uniform<T> {
+    fn acceleration(&self, _pos: Vec2<T>, _vel: Vec2<T>, _time: T) -> Vec2<T> {
+        self.acceleration
+    }
+}
+
+/// Pulls particles towards `center` (or pushes them away if `strength` is negative).
+/// The force fades linearly from `strength` at the center to 0 at `radius`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Radial<T = f32> {
+    pub center: Vec2<T>,
+    pub radius: T,
+    pub strength: T,
+}
+
+impl<T: Float> ForceField<T> for Radial<T> {
+    fn acceleration(&self, pos: Vec2<T>, _vel: Vec2<T>, _time: T) -> Vec2<T> {
+        let diff = self.center - pos;
+        let falloff = T::ONE - diff.dist() / self.radius;
+
+        if falloff <= T::ZERO {
+            return Vec2 { x: T::ZERO, y: T::ZERO };
+        }
+
+        diff.normalize() * self.strength * falloff
+    }
+}
+
+/// Spins particles around `center`, clockwise on screen if `strength` is positive.
+/// The force fades linearly from `strength` at the center to 0 at `radius`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Vortex<T = f32> {
+    pub center: Vec2<T>,
+    pub radius: T,
+    pub strength: T,
+}
+
+impl<T: Float> ForceField<T> for Vortex<T> {
+    fn acceleration(&self, pos: Vec2<T>, _vel: Vec2<T>, _time: T) -> Vec2<T> {
+        let diff = pos - self.center;
+        let falloff = T::ONE - diff.dist() / self.radius;
+
+        if falloff <= T::ZERO {
+            return Vec2 { x: T::ZERO, y: T::ZERO };
+        }
+
+        let tangent = Vec2 { x: -diff.y, y: diff.x }.normalize();
+        tangent * self.strength * falloff
+    }
+}
+
+/// Drags particles inside `region` towards `velocity`.
+/// A higher `drag` makes particles reach the wind's velocity sooner.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Wind<T = f32> {
+    pub region: Rect<T>,
+    pub velocity: Vec2<T>,
+    pub drag: T,
+}
+
+impl<T: Float> ForceField<T> for Wind<T> {
+    fn acceleration(&self, pos: Vec2<T>, vel: Vec2<T>, _time: T) -> Vec2<T> {
+        let inside = pos.x >= self.region.min.x
+            && pos.x <= self.region.max.x
+            && pos.y >= self.region.min.y
+            && pos.y <= self.region.max.y;
+
+        if !inside {
+            return Vec2 { x: T::ZERO, y: T::ZERO };
+        }
+
+        (self.velocity - vel) * self.drag
+    }
+}
+
+/// Random but smooth accelerations that change over space and time.
+/// `scale` is the size of the swirls, `speed` is how fast they change.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Turbulence<T = f32> {
+    pub strength: T,
+    pub scale: T,
+    pub speed: T,
+    pub seed: u32,
+}
+
+impl<T: Float> ForceField<T> for Turbulence<T> {
+    fn acceleration(&self, pos: Vec2<T>, _vel: Vec2<T>, time: T) -> Vec2<T> {
+        let x = (pos.x / self.scale).to_f64();
+        let y = (pos.y / self.scale).to_f64();
+        let t = (time * self.speed).to_f64();
+
+        Vec2 {
+            x: T::from_f64(value_noise(x, y, t, self.seed)) * self.strength,
+            y: T::from_f64(value_noise(x, y, t, self.seed.wrapping_add(1))) * self.strength,
+        }
+    }
+}
+
+// smoothly interpolated random values on an integer lattice, in -1..1.
+fn value_noise(x: f64, y: f64, z: f64, seed: u32) -> f64 {
+    let (x0, y0, z0) = (x.floor(), y.floor(), z.floor());
+    let smooth = |t: f64| t * t * (3. - 2. * t);
+    let (tx, ty, tz) = (smooth(x - x0), smooth(y - y0), smooth(z - z0));
+    let (x0, y0, z0) = (x0 as i64, y0 as i64, z0 as i64);
+
+    let lattice = |dx: i64, dy: i64, dz: i64| lattice_value(x0 + dx, y0 + dy, z0 + dz, seed);
+    let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+
+    let z_0 = lerp(
+        lerp(lattice(0, 0, 0), lattice(1, 0, 0), tx),
+        lerp(lattice(0, 1, 0), lattice(1, 1, 0), tx),
+        ty,
+    );
+    let z_1 = lerp(
+        lerp(lattice(0, 0, 1), lattice(1, 0, 1), tx),
+        lerp(lattice(0, 1, 1), lattice(1, 1, 1), tx),
+        ty,
+    );
+
+    lerp(z_0, z_1, tz)
+}
+
+// hashes a lattice point to a value in -1..1.
+fn lattice_value(x: i64, y: i64, z: i64, seed: u32) -> f64 {
+    let mut hash = (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+        ^ (z as u64).wrapping_mul(0x1656_67B1_9E37_79F9)
+        ^ (seed as u64).wrapping_mul(0x27D4_EB2F_1656_67C5);
+
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xFF51_AFD7_ED55_8CCD);
+    hash ^= hash >> 33;
+
+    (hash >> 11) as f64 / (1u64 << 53) as f64 * 2. - 1.
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ZERO: Vec2 = Vec2 { x: 0., y: 0. };
+
+    #[test]
+    fn radial() {
+        let attractor = Radial { center: Vec2 { x: 10., y: 0. }, radius: 20., strength: 4. };
+
+        let acceleration = attractor.acceleration(ZERO, ZERO, 0.);
+        assert!((acceleration.x - 2.).abs() < 1e-6 && acceleration.y == 0.);
+
+        let acceleration = attractor.acceleration(Vec2 { x: 40., y: 0. }, ZERO, 0.);
+        assert_eq!(acceleration, ZERO);
+    }
+
+    #[test]
+    fn vortex() {
+        let vortex = Vortex { center: ZERO, radius: 20., strength: 4. };
+        let pos = Vec2 { x: 10., y: 0. };
+
+        let acceleration = vortex.acceleration(pos, ZERO, 0.);
+        assert!(acceleration.x.abs() < 1e-6 && (acceleration.y - 2.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn wind() {
+        let wind = Wind {
+            region: Rect { min: ZERO, max: Vec2 { x: 10., y: 10. } },
+            velocity: Vec2 { x: 5., y: 0. },
+            drag: 2.,
+        };
+
+        assert_eq!(wind.acceleration(Vec2 { x: 5., y: 5. }, ZERO, 0.), Vec2 { x: 10., y: 0. });
+        assert_eq!(wind.acceleration(Vec2 { x: 15., y: 5. }, ZERO, 0.), ZERO);
+    }
+
+    #[test]
+    fn turbulence_is_smooth() {
+        let turbulence = Turbulence { strength: 1., scale: 10., speed: 1., seed: 3 };
+
+        let a = turbulence.acceleration(Vec2 { x: 12., y: 7. }, ZERO, 0.5);
+        let b = turbulence.acceleration(Vec2 { x: 12.01, y: 7. }, ZERO, 0.5);
+
+        assert!(a.x.abs() <= 1. && a.y.abs() <= 1.);
+        assert!(Vec2::dist(a - b) < 1e-2);
+    }
+}
@@ -0,0 +1,148 @@
+//! Records the simulation window to an animated GIF.
+
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use eframe::egui;
+use egui::*;
+
+#[derive(Debug, Clone)]
+pub struct GifCapture {
+    pub path: String,
+    /// Seconds to record for.
+    pub duration: f32,
+    pub fps: u32,
+    capture: Option<Capture>,
+    // written by the encoding thread
+    status: Arc<Mutex<String>>,
+}
+
+#[derive(Debug, Clone)]
+struct Capture {
+    frames: Vec<Arc<ColorImage>>,
+    // `ctx` time when recording started
+    started: f64,
+    // `ctx` time the next frame is due
+    next_frame: f64,
+    // whether a screenshot was requested and hasn't arrived yet
+    pending: bool,
+}
+
+impl Default for GifCapture {
+    fn default() -> GifCapture {
+        GifCapture {
+            path: "recording.gif".to_string(),
+            duration: 5.,
+            fps: 15,
+            capture: None,
+            status: Arc::new(Mutex::new(String::new())),
+        }
+    }
+}
+
+impl GifCapture {
+    pub fn side_panel(&mut self, ui: &mut Ui) {
+        ui.heading("gif");
+
+        ui.text_edit_singleline(&mut self.path);
+        ui.add(Slider::new(&mut self.duration, 1.0..=30.).text("duration"));
+        ui.add(Slider::new(&mut self.fps, 5..=30).text("fps"));
+
+        match &self.capture {
+            Some(capture) => {
+                let elapsed = ui.ctx().input(|i| i.time) - capture.started;
+                ui.label(format!("recording {elapsed:.1}s / {:.1}s", self.duration));
+
+                if ui.button("stop gif").clicked() {
+                    self.finish();
+                }
+            }
+            None => {
+                if ui.button("record gif").clicked() {
+                    let now = ui.ctx().input(|i| i.time);
+
+                    self.capture = Some(Capture { frames: vec![], started: now, next_frame: now, pending: false });
+                    *self.status.lock().unwrap() = String::new();
+                }
+            }
+        }
+
+        ui.label(&*self.status.lock().unwrap());
+    }
+
+    /// Called every frame with the area of the screen to record.
+    pub fn update(&mut self, ctx: &Context, rect: egui::Rect) {
+        let Some(capture) = &mut self.capture else {
+            return;
+        };
+
+        let now = ctx.input(|i| i.time);
+
+        for event in ctx.input(|i| i.events.clone()) {
+            if let Event::Screenshot { image, .. } = event {
+                let region = rect.intersect(ctx.screen_rect());
+                capture.frames.push(Arc::new(image.region(&region, Some(ctx.pixels_per_point()))));
+                capture.pending = false;
+            }
+        }
+
+        if now - capture.started >= self.duration as f64 {
+            self.finish();
+            return;
+        }
+
+        if now >= capture.next_frame && !capture.pending {
+            ctx.send_viewport_cmd(ViewportCommand::Screenshot(UserData::default()));
+            capture.next_frame += 1. / self.fps as f64;
+            capture.pending = true;
+        }
+
+        ctx.request_repaint();
+    }
+
+    // stops recording and encodes the frames on another thread.
+    fn finish(&mut self) {
+        let Some(capture) = self.capture.take() else {
+            return;
+        };
+
+        let path = self.path.clone();
+        let fps = self.fps;
+        let status = Arc::clone(&self.status);
+        *status.lock().unwrap() = format!("encoding {} frames", capture.frames.len());
+
+        thread::spawn(move || {
+            *status.lock().unwrap() = match encode_gif(&path, &capture.frames, fps) {
+                Ok(()) => format!("saved {path}"),
+                Err(err) => err.to_string(),
+            };
+        });
+    }
+}
+
+// frames that aren't the same size as the first one (e.g. because the
+// window was resized) are skipped.
+fn encode_gif(path: &str, frames: &[Arc<ColorImage>], fps: u32) -> io::Result<()> {
+    let Some(first) = frames.first() else {
+        return Err(io::Error::other("no frames were captured"));
+    };
+    let [width, height] = first.size;
+
+    let mut encoder = gif::Encoder::new(BufWriter::new(File::create(path)?), width as u16, height as u16, &[])
+        .map_err(io::Error::other)?;
+    encoder.set_repeat(gif::Repeat::Infinite).map_err(io::Error::other)?;
+
+    for image in frames.iter().filter(|image| image.size == first.size) {
+        let mut rgba: Vec<u8> = image.pixels.iter().flat_map(|pixel| pixel.to_array()).collect();
+
+        let mut frame = gif::Frame::from_rgba_speed(width as u16, height as u16, &mut rgba, 10);
+        // in hundredths of a second
+        frame.delay = (100 / fps) as u16;
+
+        encoder.write_frame(&frame).map_err(io::Error::other)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,264 @@
+use std::ops::{Add, Mul};
+
+use crate::*;
+
+/// Values sampled at the nodes of a regular grid covering `rect`, row by
+/// row from `rect.min`. Nodes lie on the edges of `rect`, so there are
+/// `width - 1` cells along x.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grid<V = f64> {
+    pub rect: Rect<f64>,
+    pub width: usize,
+    pub height: usize,
+    pub values: Vec<V>,
+}
+
+impl<V: Copy> Grid<V> {
+    /// `width` and `height` are at least 2.
+    pub fn new(rect: Rect<f64>, width: usize, height: usize, value: V) -> Grid<V> {
+        let (width, height) = (width.max(2), height.max(2));
+
+        Grid {
+            rect,
+            width,
+            height,
+            values: vec![value; width * height],
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> V {
+        self.values[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, value: V) {
+        self.values[y * self.width + x] = value;
+    }
+
+    /// The distance between neighboring nodes.
+    pub fn spacing(&self) -> Vec2<f64> {
+        Vec2 {
+            x: self.rect.width() / (self.width - 1) as f64,
+            y: self.rect.height() / (self.height - 1) as f64,
+        }
+    }
+
+    pub fn node_pos(&self, x: usize, y: usize) -> Vec2<f64> {
+        let spacing = self.spacing();
+
+        Vec2 {
+            x: self.rect.min.x + x as f64 * spacing.x,
+            y: self.rect.min.y + y as f64 * spacing.y,
+        }
+    }
+}
+
+impl<V: Copy + Add<Output = V> + Mul<f64, Output = V>> Grid<V> {
+    /// Bilinearly interpolates the values at `pos`. Returns `None` outside `rect`.
+    pub fn interpolate(&self, pos: Vec2<f64>) -> Option<V> {
+        let spacing = self.spacing();
+        let x = (pos.x - self.rect.min.x) / spacing.x;
+        let y = (pos.y - self.rect.min.y) / spacing.y;
+
+        if !(0. ..=(self.width - 1) as f64).contains(&x) || !(0. ..=(self.height - 1) as f64).contains(&y) {
+            return None;
+        }
+
+        // the cell's min node, and how far `pos` is into the cell
+        let (x0, y0) = ((x as usize).min(self.width - 2), (y as usize).min(self.height - 2));
+        let (tx, ty) = (x - x0 as f64, y - y0 as f64);
+
+        let bottom = self.get(x0, y0) * (1. - tx) + self.get(x0 + 1, y0) * tx;
+        let top = self.get(x0, y0 + 1) * (1. - tx) + self.get(x0 + 1, y0 + 1) * tx;
+
+        Some(bottom * (1. - ty) + top * ty)
+    }
+}
+
+impl Grid<Vec2<f64>> {
+    /// Follows the velocities from `seed` in steps of `step_length` with the
+    /// midpoint method, for at most `max_points` points. Stops where the
+    /// velocity is 0 or at the edge of the grid.
+    pub fn streamline(&self, seed: Vec2<f64>, step_length: f64, max_points: usize) -> Vec<Vec2<f64>> {
+        // the unit direction of the flow at `pos`
+        let direction = |pos: Vec2<f64>| {
+            self.interpolate(pos)
+                .filter(|velocity| velocity.dist() > 1e-9)
+                .map(Vec2::normalize)
+        };
+
+        let mut points = vec![];
+        let mut pos = seed;
+
+        while points.len() < max_points {
+            points.push(pos);
+
+            let Some(start) = direction(pos) else { break };
+            let Some(mid) = direction(pos + start * (step_length / 2.)) else { break };
+            pos += mid * step_length;
+        }
+
+        points
+    }
+}
+
+/// A field `Simulation::sample_field` can sample.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GridField {
+    Density,
+    Velocity,
+}
+
+/// The grid returned by `Simulation::sample_field`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldGrid {
+    Density(Grid<f64>),
+    Velocity(Grid<Vec2<f64>>),
+}
+
+impl<T: Float> Simulation<T> {
+    /// Samples `field` at `resolution.0` by `resolution.1` nodes covering
+    /// `rect`, see `sample_density` and `sample_velocity`.
+    pub fn sample_field(&self, field: GridField, rect: Rect<T>, resolution: (usize, usize)) -> FieldGrid {
+        match field {
+            GridField::Density => FieldGrid::Density(self.sample_density(rect, resolution)),
+            GridField::Velocity => FieldGrid::Velocity(self.sample_velocity(rect, resolution)),
+        }
+    }
+
+    /// Samples the particle density with the same kernel as the density
+    /// relaxation.
+    pub fn sample_density(&self, rect: Rect<T>, resolution: (usize, usize)) -> Grid {
+        let mut grid = Grid::new(rect_to_f64(rect), resolution.0, resolution.1, 0.);
+        let mut values = grid.values.clone();
+
+        self.splat(&grid, |i, weight, _| values[i] += weight);
+
+        grid.values = values;
+        grid
+    }
+
+    /// Samples the particle velocity, weighted by the density kernel. Nodes
+    /// with no particle within the interaction radius are 0.
+    pub fn sample_velocity(&self, rect: Rect<T>, resolution: (usize, usize)) -> Grid<Vec2<f64>> {
+        let mut grid = Grid::new(rect_to_f64(rect), resolution.0, resolution.1, Vec2 { x: 0., y: 0. });
+        let mut values = grid.values.clone();
+        let mut weights = vec![0.; values.len()];
+
+        self.splat(&grid, |i, weight, particle| {
+            values[i] += Vec2 { x: particle.vel.x.to_f64(), y: particle.vel.y.to_f64() } * weight;
+            weights[i] += weight;
+        });
+
+        for (value, weight) in values.iter_mut().zip(weights) {
+            if weight > 0. {
+                *value /= weight;
+            }
+        }
+
+        grid.values = values;
+        grid
+    }
+
+    // calls `f` with the index of every node within the interaction radius
+    // of a particle, the density kernel's weight there and the particle.
+    fn splat<V: Copy>(&self, grid: &Grid<V>, mut f: impl FnMut(usize, f64, &Particle<T>)) {
+        let rect = grid.rect;
+        let spacing = grid.spacing();
+        let radius = self.interaction_radius().to_f64();
+
+        for particle in self.particles() {
+            let pos = Vec2 { x: particle.pos.x.to_f64(), y: particle.pos.y.to_f64() };
+
+            // the nodes within `radius` of the particle
+            let min_x = ((pos.x - radius - rect.min.x) / spacing.x).ceil().max(0.) as usize;
+            let min_y = ((pos.y - radius - rect.min.y) / spacing.y).ceil().max(0.) as usize;
+            let max_x = ((pos.x + radius - rect.min.x) / spacing.x).floor().min((grid.width - 1) as f64);
+            let max_y = ((pos.y + radius - rect.min.y) / spacing.y).floor().min((grid.height - 1) as f64);
+
+            if max_x < 0. || max_y < 0. {
+                continue;
+            }
+
+            for y in min_y..=max_y as usize {
+                for x in min_x..=max_x as usize {
+                    let q = Vec2::dist(grid.node_pos(x, y) - pos) / radius;
+
+                    if q < 1. {
+                        f(y * grid.width + x, (1. - q) * (1. - q), particle);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn rect_to_f64<T: Float>(rect: Rect<T>) -> Rect<f64> {
+    Rect {
+        min: Vec2 { x: rect.min.x.to_f64(), y: rect.min.y.to_f64() },
+        max: Vec2 { x: rect.max.x.to_f64(), y: rect.max.y.to_f64() },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_density() {
+        let particles = vec![
+            Particle::new(Vec2 { x: 50., y: 50. }, Vec2 { x: 0., y: 0. }),
+            Particle::new(Vec2 { x: 70., y: 50. }, Vec2 { x: 0., y: 0. }),
+        ];
+        let simulation = Simulation::with_particles(particles);
+
+        let rect = Rect { min: Vec2 { x: 0., y: 0. }, max: Vec2 { x: 100., y: 100. } };
+        let grid = simulation.sample_density(rect, (11, 11));
+
+        assert_eq!(grid.node_pos(5, 5), Vec2 { x: 50., y: 50. });
+        // 1 from the particle on the node, (1 - 20 / 40)² from the other one
+        assert_eq!(grid.get(5, 5), 1.25);
+        assert_eq!(grid.get(0, 0), 0.);
+    }
+
+    #[test]
+    fn sample_velocity() {
+        let particles = vec![
+            Particle::new(Vec2 { x: 50., y: 50. }, Vec2 { x: 10., y: 0. }),
+            Particle::new(Vec2 { x: 70., y: 50. }, Vec2 { x: 0., y: 10. }),
+        ];
+        let simulation = Simulation::with_particles(particles);
+
+        let rect = Rect { min: Vec2 { x: 0., y: 0. }, max: Vec2 { x: 100., y: 100. } };
+        let FieldGrid::Velocity(grid) = simulation.sample_field(GridField::Velocity, rect, (11, 11)) else {
+            panic!("expected a velocity grid");
+        };
+
+        // halfway between the particles, both weigh the same
+        assert_eq!(grid.get(6, 5), Vec2 { x: 5., y: 5. });
+        assert_eq!(grid.get(0, 0), Vec2 { x: 0., y: 0. });
+    }
+
+    #[test]
+    fn streamline() {
+        let rect = Rect { min: Vec2 { x: -10., y: -10. }, max: Vec2 { x: 10., y: 10. } };
+        let mut grid = Grid::new(rect, 41, 41, Vec2 { x: 0., y: 0. });
+
+        // turning counterclockwise around the origin
+        for y in 0..grid.height {
+            for x in 0..grid.width {
+                let pos = grid.node_pos(x, y);
+                grid.set(x, y, Vec2 { x: -pos.y, y: pos.x });
+            }
+        }
+
+        assert_eq!(grid.interpolate(Vec2 { x: 1.25, y: 0. }), Some(Vec2 { x: 0., y: 1.25 }));
+        assert_eq!(grid.interpolate(Vec2 { x: 11., y: 0. }), None);
+
+        // stays on the circle it started on
+        let points = grid.streamline(Vec2 { x: 5., y: 0. }, 0.5, 40);
+        assert_eq!(points.len(), 40);
+        for point in points {
+            assert!((point.dist() - 5.).abs() < 0.05);
+        }
+    }
+}
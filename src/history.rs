@@ -0,0 +1,204 @@
+//! Frames of the running simulation the viewer can rewind to.
+
+use std::collections::VecDeque;
+
+use fluid_sim::{Particle, Simulation, Vec2};
+
+/// A ring buffer of frames, one every `interval` steps.
+/// Once `capacity` frames are kept, the oldest ones are dropped.
+#[derive(Debug, Clone)]
+pub struct History {
+    frames: VecDeque<HistoryFrame>,
+    capacity: usize,
+    interval: usize,
+    // steps since the last frame
+    steps: usize,
+}
+
+/// The particles at one point in time. Everything else, e.g. the settings,
+/// obstacles and emitters, is taken from the simulation it's restored into.
+#[derive(Debug, Clone)]
+pub struct HistoryFrame {
+    time: f32,
+    particles: Vec<HistoryParticle>,
+}
+
+// the part of `Particle` that changes from step to step. densities and
+// pressures are computed again by the next step.
+#[derive(Debug, Clone, Copy)]
+struct HistoryParticle {
+    pos: Vec2,
+    vel: Vec2,
+    material: u32,
+    age: f32,
+}
+
+impl History {
+    pub fn new(capacity: usize, interval: usize) -> History {
+        History {
+            frames: VecDeque::with_capacity(capacity),
+            capacity,
+            interval,
+            steps: 0,
+        }
+    }
+
+    /// Called once before the first step, and after every step.
+    pub fn record(&mut self, simulation: &Simulation) {
+        if !self.frames.is_empty() {
+            self.steps += 1;
+
+            if self.steps < self.interval {
+                return;
+            }
+        }
+
+        if self.frames.len() == self.capacity {
+            self.frames.pop_front();
+        }
+
+        self.frames.push_back(HistoryFrame::new(simulation));
+        self.steps = 0;
+    }
+
+    /// The number of steps between two frames.
+    pub fn interval(&self) -> usize {
+        self.interval
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn get(&self, frame: usize) -> Option<&HistoryFrame> {
+        self.frames.get(frame)
+    }
+
+    /// Forgets every frame after `frame`, so recording continues from it as
+    /// if it had just been recorded.
+    pub fn resume_from(&mut self, frame: usize) {
+        self.frames.truncate(frame + 1);
+        self.steps = 0;
+    }
+}
+
+impl HistoryFrame {
+    fn new(simulation: &Simulation) -> HistoryFrame {
+        HistoryFrame {
+            time: simulation.time(),
+            particles: simulation
+                .particles()
+                .iter()
+                .map(|particle| HistoryParticle {
+                    pos: particle.pos,
+                    vel: particle.vel,
+                    material: particle.material,
+                    age: particle.age,
+                })
+                .collect(),
+        }
+    }
+
+    /// Puts the particles back where they were.
+    pub fn restore(&self, simulation: &mut Simulation) {
+        let mut snapshot = simulation.snapshot();
+        snapshot.time = self.time;
+        snapshot.particles = self
+            .particles
+            .iter()
+            .map(|particle| Particle {
+                prev_pos: particle.pos,
+                age: particle.age,
+                ..Particle::new(particle.pos, particle.vel).with_material(particle.material)
+            })
+            .collect();
+
+        simulation.restore(&snapshot);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use fluid_sim::{Rect, SimulationBuilder};
+
+    fn test_simulation() -> Simulation {
+        let particles = (0..20)
+            .map(|i| Particle::new(Vec2 { x: 20. + (i % 5) as f32 * 10., y: 20. + (i / 5) as f32 * 10. }, Vec2 { x: 0., y: 0. }).with_material(i % 3))
+            .collect();
+
+        let mut simulation = SimulationBuilder::default()
+            .with_boundaries(Rect { min: Vec2 { x: 0., y: 0. }, max: Vec2 { x: 200., y: 200. } })
+            .with_particles(particles)
+            .build();
+        simulation.init();
+        simulation
+    }
+
+    fn run(history: &mut History, simulation: &mut Simulation, steps: usize) {
+        for _ in 0..steps {
+            simulation.step(1. / 60.);
+            history.record(simulation);
+        }
+    }
+
+    #[test]
+    fn interval_and_capacity() {
+        let mut simulation = test_simulation();
+        let mut history = History::new(3, 2);
+        history.record(&simulation);
+
+        run(&mut history, &mut simulation, 1);
+        assert_eq!(history.len(), 1);
+        run(&mut history, &mut simulation, 1);
+        assert_eq!(history.len(), 2);
+        let time = simulation.time();
+
+        // steps 0, 2, 4 and 6, the first of which is dropped
+        run(&mut history, &mut simulation, 4);
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.get(0).unwrap().time, time);
+        assert_eq!(history.get(2).unwrap().time, simulation.time());
+    }
+
+    #[test]
+    fn resume_from() {
+        let mut simulation = test_simulation();
+        let mut history = History::new(10, 1);
+        history.record(&simulation);
+        run(&mut history, &mut simulation, 4);
+
+        history.get(1).unwrap().clone().restore(&mut simulation);
+        history.resume_from(1);
+        assert_eq!(history.len(), 2);
+
+        run(&mut history, &mut simulation, 1);
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.get(2).unwrap().time, simulation.time());
+    }
+
+    #[test]
+    fn restore() {
+        let mut simulation = test_simulation();
+        let mut history = History::new(30, 1);
+        history.record(&simulation);
+        run(&mut history, &mut simulation, 10);
+
+        let frame = history.get(5).unwrap().clone();
+        run(&mut history, &mut simulation, 10);
+        frame.restore(&mut simulation);
+
+        assert_eq!(simulation.time(), frame.time);
+        for (particle, saved) in simulation.particles().iter().zip(&frame.particles) {
+            assert_eq!(particle.pos, saved.pos);
+            assert_eq!(particle.prev_pos, saved.pos);
+            assert_eq!(particle.vel, saved.vel);
+            assert_eq!(particle.material, saved.material);
+            assert_eq!(particle.age, saved.age);
+        }
+
+        // the particles moved since the frame was recorded
+        assert_ne!(history.get(15).unwrap().particles[0].pos, frame.particles[0].pos);
+    }
+}
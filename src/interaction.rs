@@ -0,0 +1,56 @@
+use crate::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InteractionKind {
+    /// Pushes particles away from the center.
+    Push,
+    /// Pulls particles towards the center.
+    Pull,
+    /// Grabs the particles within `radius` when it starts, and pulls each
+    /// of them towards where it was relative to the center with a spring.
+    /// See `Simulation::set_interaction`.
+    Drag,
+}
+
+/// A force applied by a user (usually with the mouse) around `center`.
+/// The force fades linearly from `strength` at the center to 0 at `radius`,
+/// except for `InteractionKind::Drag`, where `strength` is the spring's
+/// stiffness in 1/s².
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Interaction<T = f32> {
+    pub kind: InteractionKind,
+    pub center: Vec2<T>,
+    pub radius: T,
+    pub strength: T,
+    /// How fast the center is moving. Only used by `InteractionKind::Drag`.
+    pub velocity: Vec2<T>,
+}
+
+impl<T: Float> Interaction<T> {
+    /// The acceleration of a grabbed particle `to_target` away from where it's
+    /// held, critically damped towards the center's velocity.
+    pub fn drag_acceleration(&self, to_target: Vec2<T>, vel: Vec2<T>) -> Vec2<T> {
+        let damping = T::from_f32(2.) * self.strength.sqrt();
+        to_target * self.strength + (self.velocity - vel) * damping
+    }
+}
+
+impl<T: Float> ForceField<T> for Interaction<T> {
+    fn acceleration(&self, pos: Vec2<T>, _vel: Vec2<T>, _time: T) -> Vec2<T> {
+        let diff = pos - self.center;
+        let falloff = T::ONE - diff.dist() / self.radius;
+
+        if falloff <= T::ZERO {
+            return Vec2 { x: T::ZERO, y: T::ZERO };
+        }
+
+        match self.kind {
+            InteractionKind::Push => diff.normalize() * self.strength * falloff,
+            InteractionKind::Pull => -diff.normalize() * self.strength * falloff,
+            // grabbed particles are pulled by `Simulation::step` instead
+            InteractionKind::Drag => Vec2 { x: T::ZERO, y: T::ZERO },
+        }
+    }
+}
@@ -1,6 +1,21 @@
+pub mod float;
+pub use float::*;
+
+mod parallel;
+use parallel::*;
+
 pub mod particle;
 pub use particle::*;
 
+pub mod vector;
+pub use vector::*;
+
+mod cell_grid;
+use cell_grid::*;
+
+mod relaxation;
+use relaxation::*;
+
 pub mod vec2;
 pub use vec2::*;
 
@@ -10,23 +25,106 @@ pub use rect::*;
 pub mod simulation_builder;
 pub use simulation_builder::*;
 
-use std::collections::HashMap;
+pub mod soa;
+pub use soa::*;
+
+pub mod boundary;
+pub use boundary::*;
+
+pub mod force_field;
+pub use force_field::*;
+
+pub mod interaction;
+pub use interaction::*;
+
+pub mod obstacle;
+pub use obstacle::*;
+
+pub mod emitter;
+pub use emitter::*;
+
+#[cfg(feature = "serde")]
+pub mod scene;
+#[cfg(feature = "serde")]
+pub use scene::*;
+
+pub mod snapshot;
+pub use snapshot::*;
+
+pub mod export;
+pub use export::*;
+
+#[cfg(feature = "trajectory")]
+pub mod trajectory;
+#[cfg(feature = "trajectory")]
+pub use trajectory::*;
 
+pub mod render;
+pub use render::*;
+
+pub mod colormap;
+pub use colormap::*;
+
+pub mod color_field;
+pub use color_field::*;
+
+pub mod grid;
+pub use grid::*;
+
+pub mod contour;
+pub use contour::*;
+
+pub mod vec3;
+pub use vec3::*;
+
+pub mod aabb;
+pub use aabb::*;
+
+pub mod particle3;
+pub use particle3::*;
+
+pub mod simulation3;
+pub use simulation3::*;
+
+pub mod simulation_builder3;
+pub use simulation_builder3::*;
+
+use std::sync::Arc;
+
+// `cells` aren't serialized, so `Deserialize` is implemented by hand below
+// to rebuild them after loading.
 #[derive(Debug)]
-pub struct Simulation {
-    pub gravity: f32,
-    pub boundaries: Rect,
-    interaction_radius: f32,
-    pub pressure_multiplier: f32,
-    pub near_pressure_multiplier: f32,
-    pub rest_density: f32,
-    particles: Vec<Particle>,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(remote = "Self"))]
+pub struct Simulation<T = f32> {
+    pub gravity: Vec2<T>,
+    /// Applied to every particle in addition to `gravity`.
+    /// Not serialized, since force fields are trait objects.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub force_fields: Vec<Arc<dyn ForceField<T>>>,
+    pub obstacles: Vec<Obstacle<T>>,
+    pub emitters: Vec<Emitter<T>>,
+    pub boundaries: Rect<T>,
+    pub boundary_x: BoundaryMode,
+    pub boundary_y: BoundaryMode,
+    interaction_radius: T,
+    pub pressure_multiplier: T,
+    pub near_pressure_multiplier: T,
+    pub rest_density: T,
+    pub kernel: RelaxationKernel,
+    interaction: Option<Interaction<T>>,
+    /// The particles grabbed by a drag interaction, with their offsets from
+    /// its center.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    grabbed: Vec<(usize, Vec2<T>)>,
+    time: T,
+    particles: Vec<Particle<T>>,
     /// One cell is `interaction_radius * 2.` by `interaction_radius * 2.`.
     /// Cell `(0, 0)` starts from `(0., 0.)` to `(interaction_radius * 2., interaction_radius * 2.)`.
-    cells: HashMap<(i32, i32), Vec<usize>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    cells: CellGrid<(i32, i32)>,
 }
 
-impl Simulation {
+impl<T: Float> Simulation<T> {
     // TODO: maybe instead of setting prev_pos to current pos, we should
     // change the cell_index field in Particle to Option<usize>?
     
@@ -40,11 +138,11 @@ impl Simulation {
         }
     }
 
-    pub fn interaction_radius(&self) -> f32 {
+    pub fn interaction_radius(&self) -> T {
         self.interaction_radius
     }
 
-    pub fn set_interaction_radius(&mut self, new_interaction_radius: f32) {
+    pub fn set_interaction_radius(&mut self, new_interaction_radius: T) {
         self.interaction_radius = new_interaction_radius;
         self.update_cells();
 
@@ -57,216 +155,767 @@ impl Simulation {
     // clear all cells, then
     // loops through all particles and puts them in the correct cell.
     fn update_cells(&mut self) {
-        for (_, cell) in self.cells.iter_mut() {
-            cell.clear();
-        }
-        
+        self.cells.empty();
+
         // update cells
         for i in 0..self.particles.len() {
-            let particle = self.particles[i];
-            let cell = self.get_cell_key(particle.pos);
-            self.add_to_cell(i, cell);
+            let cell = self.get_cell_key(self.particles[i].pos);
+            self.cells.add(&mut self.particles, i, cell);
         }
     }
     
-    pub fn with_particles(particles: Vec<Particle>) -> Simulation {
+    pub fn with_particles(particles: Vec<Particle<T>>) -> Simulation<T> {
         Simulation {
             particles,
             ..Simulation::default()
         }
     }
     
-    pub fn particles(&self) -> &Vec<Particle> {
+    pub fn particles(&self) -> &Vec<Particle<T>> {
         &self.particles
     }
 
-    pub fn step(&mut self, dt: f32) {
-        use rayon::prelude::*;
+    /// Seconds simulated since the simulation was built.
+    pub fn time(&self) -> T {
+        self.time
+    }
+
+    pub fn interaction(&self) -> Option<Interaction<T>> {
+        self.interaction
+    }
+
+    /// Sets the user interaction applied on every step, until it is set again.
+    /// A drag grabs the particles within its radius when it starts, and holds
+    /// on to them until it's replaced by another kind of interaction or `None`.
+    pub fn set_interaction(&mut self, interaction: Option<Interaction<T>>) {
+        let dragging = |interaction: Option<Interaction<T>>| {
+            interaction.is_some_and(|interaction| interaction.kind == InteractionKind::Drag)
+        };
+
+        if !dragging(interaction) {
+            self.grabbed.clear();
+        } else if let (Some(drag), false) = (interaction, dragging(self.interaction)) {
+            self.grabbed = (0..self.particles.len())
+                .map(|i| (i, self.periodic_diff(drag.center, self.particles[i].pos)))
+                .filter(|(_, offset)| offset.dist() <= drag.radius)
+                .collect();
+        }
+
+        self.interaction = interaction;
+    }
+
+    /// The indices of the particles held by the current drag interaction.
+    pub fn grabbed(&self) -> impl Iterator<Item = usize> + '_ {
+        self.grabbed.iter().map(|&(i, _)| i)
+    }
+
+    pub fn add_particle(&mut self, mut particle: Particle<T>) {
+        particle.prev_pos = particle.pos;
+        self.particles.push(particle);
+
+        let index = self.particles.len() - 1;
+        let cell = self.get_cell_key(particle.pos);
+        self.cells.add(&mut self.particles, index, cell);
+    }
+
+    /// Removes every particle within `radius` of `center`.
+    /// Returns the number of removed particles.
+    pub fn remove_particles_in(&mut self, center: Vec2<T>, radius: T) -> usize {
+        let len = self.particles.len();
+
+        self.particles.retain(|particle| Vec2::dist(particle.pos - center) > radius);
 
-        self.particles.par_iter_mut().for_each(|particle| {
-            if particle.pos.x < self.boundaries.min.x {
-                particle.pos.x = self.boundaries.min.x;
-                particle.vel.x *= -0.5;
+        let removed = len - self.particles.len();
+        if removed > 0 {
+            // indices have shifted, so the cells are rebuilt from scratch
+            // and grabbed particles are let go
+            self.update_cells();
+            self.grabbed.clear();
+
+            // the particles are now in the cells of their current pos
+            for particle in &mut self.particles {
+                particle.prev_pos = particle.pos;
             }
-        
-            if particle.pos.x > self.boundaries.max.x {
-                particle.pos.x = self.boundaries.max.x;
-                particle.vel.x *= -0.5;
+        }
+
+        removed
+    }
+
+    /// Returns the vorticity (the curl of the velocity) around every particle,
+    /// estimated from its neighbors' relative velocities. Positive values
+    /// turn from +x towards +y.
+    pub fn vorticities(&self) -> Vec<T> {
+        let mut vorticities = vec![T::ZERO; self.particles.len()];
+
+        for (cell, indices) in self.cells.iter() {
+            let neighbors = self.neighbors_from_cell(*cell);
+
+            for &i in indices {
+                let mut curl = T::ZERO;
+                let mut weights = T::ZERO;
+
+                for &j in &neighbors {
+                    if i == j { continue; }
+
+                    let diff = self.periodic_diff(self.particles[i].pos, self.particles[j].pos);
+                    let dvel = self.particles[j].vel - self.particles[i].vel;
+                    let dist = Vec2::dist(diff);
+                    let q = dist / self.interaction_radius;
+
+                    if q < T::ONE && dist > T::ZERO {
+                        // exact for a rigid rotation
+                        let weight = T::ONE - q;
+                        curl += weight * (diff.x * dvel.y - diff.y * dvel.x) / (dist * dist);
+                        weights += weight;
+                    }
+                }
+
+                if weights > T::ZERO {
+                    // the weighted average is the angular velocity, half the curl
+                    vorticities[i] = T::from_f32(2.) * curl / weights;
+                }
             }
-        
-            if particle.pos.y < self.boundaries.min.y {
-                particle.pos.y = self.boundaries.min.y;
-                particle.vel.y *= -0.5;
+        }
+
+        vorticities
+    }
+
+    /// Returns the density and near density of every particle, computed
+    /// from the particles' current positions, unlike `Particle::density`.
+    pub fn densities(&self) -> Vec<(T, T)> {
+        let mut densities = vec![(T::ZERO, T::ZERO); self.particles.len()];
+
+        for (cell, indices) in self.cells.iter() {
+            let neighbors = self.neighbors_from_cell(*cell);
+
+            for &i in indices {
+                for &j in &neighbors {
+                    if i == j { continue; }
+
+                    let dist = Vec2::dist(self.periodic_diff(self.particles[j].pos, self.particles[i].pos));
+                    let q = dist / self.interaction_radius;
+
+                    if q < T::ONE {
+                        densities[i].0 += (T::ONE - q) * (T::ONE - q);
+                        densities[i].1 += (T::ONE - q) * (T::ONE - q) * (T::ONE - q);
+                    }
+                }
             }
-        
-            if particle.pos.y > self.boundaries.max.y {
-                particle.pos.y = self.boundaries.max.y;
-                particle.vel.y *= -0.5;
+        }
+
+        densities
+    }
+
+    pub fn add_force_field(&mut self, force_field: impl ForceField<T> + 'static) {
+        self.force_fields.push(Arc::new(force_field));
+    }
+
+    pub fn step(&mut self, dt: T) {
+        for e in 0..self.emitters.len() {
+            for particle in self.emitters[e].emit(dt) {
+                self.add_particle(particle);
             }
+        }
 
-            particle.vel.y += self.gravity * dt;
-        });
+        for_each_mut(&mut self.particles, |particle| {
+            let (min, max) = (self.boundaries.min, self.boundaries.max);
+            self.boundary_x.apply(&mut particle.pos.x, &mut particle.vel.x, min.x, max.x);
+            self.boundary_y.apply(&mut particle.pos.y, &mut particle.vel.y, min.y, max.y);
 
-        // update cells
-        for i in 0..self.particles.len() {
-            let particle = self.particles[i];
-            
-            let prev_cell = self.get_cell_key(particle.prev_pos);
-            let curr_cell = self.get_cell_key(particle.pos);
+            particle.vel += self.gravity * dt;
+
+            for force_field in &self.force_fields {
+                particle.vel += force_field.acceleration(particle.pos, particle.vel, self.time) * dt;
+            }
 
-            if prev_cell != curr_cell {
-                if self.cells.contains_key(&prev_cell) { self.remove_from_cell(i, prev_cell); }
-                self.add_to_cell(i, curr_cell);
+            if let Some(interaction) = &self.interaction {
+                particle.vel += interaction.acceleration(particle.pos, particle.vel, self.time) * dt;
+            }
+        });
+
+        if let Some(drag) = self.interaction {
+            for &(i, offset) in &self.grabbed {
+                let particle = self.particles[i];
+                let to_target = self.periodic_diff(particle.pos, drag.center + offset);
+                self.particles[i].vel += drag.drag_acceleration(to_target, particle.vel) * dt;
             }
         }
 
+        if self.remove_escaped_particles() {
+            // indices have shifted, so the cells are rebuilt from scratch
+            // and grabbed particles are let go
+            self.update_cells();
+            self.grabbed.clear();
+        } else {
+            self.cells.update(&mut self.particles, self.interaction_radius * T::from_f32(2.));
+        }
+
         // update pos & prev_pos
-        self.particles.par_iter_mut().for_each(|particle| {
+        for_each_mut(&mut self.particles, |particle| {
             particle.prev_pos = particle.pos;
             particle.pos += particle.vel * dt;
         });
 
         self.density_relaxation(dt);
 
+        if !self.obstacles.is_empty() {
+            let obstacles = &self.obstacles;
+
+            for_each_mut(&mut self.particles, |particle| {
+                for obstacle in obstacles {
+                    particle.pos = obstacle.push_out(particle.pos);
+                }
+            });
+        }
+
         for particle in &mut self.particles {
             particle.vel = (particle.pos - particle.prev_pos) / dt;
+            particle.age += dt;
         }
+
+        self.time += dt;
+    }
+
+    // removes the particles that left through an open boundary.
+    // returns whether any particle was removed.
+    fn remove_escaped_particles(&mut self) -> bool {
+        if self.boundary_x != BoundaryMode::Open && self.boundary_y != BoundaryMode::Open {
+            return false;
+        }
+
+        let (min, max) = (self.boundaries.min, self.boundaries.max);
+        let (boundary_x, boundary_y) = (self.boundary_x, self.boundary_y);
+        let len = self.particles.len();
+
+        self.particles.retain(|particle| {
+            !(boundary_x.escaped(particle.pos.x, min.x, max.x) || boundary_y.escaped(particle.pos.y, min.y, max.y))
+        });
+
+        self.particles.len() != len
     }
 
-    fn density_relaxation(&mut self, dt: f32) {
+    /// Returns `to - from`, going through periodic boundaries if that is shorter.
+    fn periodic_diff(&self, from: Vec2<T>, to: Vec2<T>) -> Vec2<T> {
+        periodic_diff(self.boundaries, self.boundary_x, self.boundary_y, from, to)
+    }
+
+    fn density_relaxation(&mut self, dt: T) {
+        // the chunked kernel moves each neighbor to its periodic image nearest
+        // to the cell's center, which is only the one nearest to every
+        // particle in the cell if the periodic axes are over 4 radii wide
+        let min_extent = self.interaction_radius * T::from_f32(4.);
+        let small_x = self.boundary_x == BoundaryMode::Periodic && self.boundaries.width() <= min_extent;
+        let small_y = self.boundary_y == BoundaryMode::Periodic && self.boundaries.height() <= min_extent;
+
+        match self.kernel {
+            RelaxationKernel::Chunked if !small_x && !small_y => self.density_relaxation_chunked(dt),
+            _ => self.density_relaxation_scalar(dt),
+        }
+    }
+
+    fn density_relaxation_scalar(&mut self, dt: T) {
+        let relaxation = Relaxation {
+            interaction_radius: self.interaction_radius,
+            pressure_multiplier: self.pressure_multiplier,
+            near_pressure_multiplier: self.near_pressure_multiplier,
+            rest_density: self.rest_density,
+        };
+        let (boundaries, boundary_x, boundary_y) = (self.boundaries, self.boundary_x, self.boundary_y);
+
+        for (cell, indices) in self.cells.iter() {
+            let neighbors = self.neighbors_from_cell(*cell);
+
+            relaxation.relax_cell(&mut self.particles, indices, &neighbors, dt, |from, to| {
+                periodic_diff(boundaries, boundary_x, boundary_y, from, to)
+            });
+        }
+    }
+
+    // same as `density_relaxation_scalar`, but the neighbors are copied into
+    // a `ParticleSoa` so the inner loops can be vectorized.
+    fn density_relaxation_chunked(&mut self, dt: T) {
         let interaction_radius = self.interaction_radius;
         let pressure_multiplier = self.pressure_multiplier;
         let near_pressure_multiplier = self.near_pressure_multiplier;
         let rest_density = self.rest_density;
 
+        let cell_size = interaction_radius * T::from_f32(2.);
+
+        let mut soa = ParticleSoa::new();
+        let mut displacement_x = vec![];
+        let mut displacement_y = vec![];
+        let mut shift_x = vec![];
+        let mut shift_y = vec![];
+
         for (cell, indices) in self.cells.iter() {
+            if indices.is_empty() { continue; }
+
             let neighbors = self.neighbors_from_cell(*cell);
-            
-            for &i in indices {
-                let mut density = 0.;
-                let mut near_density = 0.;
+            soa.gather(&self.particles, &neighbors);
 
-                // compute density
-                for &j in &neighbors {
-                    if i == j { continue; }
+            // move neighbors across periodic boundaries to their image nearest to this cell
+            let center = Vec2 {
+                x: (T::from_f64(cell.0 as f64) + T::from_f32(0.5)) * cell_size,
+                y: (T::from_f64(cell.1 as f64) + T::from_f32(0.5)) * cell_size,
+            };
 
-                    let particle_i = self.particles[i];
-                    let particle_j = self.particles[j];
+            shift_x.clear();
+            shift_y.clear();
 
-                    let dist = Vec2::dist(particle_i.pos - particle_j.pos);
-                    let q = dist / interaction_radius;
+            for n in 0..soa.len() {
+                let pos = Vec2 { x: soa.pos_x[n], y: soa.pos_y[n] };
+                let shift = self.periodic_diff(center, pos) - (pos - center);
 
-                    if q < 1. {
-                        density += (1. - q) * (1. - q);
-                        near_density += (1. - q) * (1. - q) * (1. - q);
-                    }
-                }
+                soa.pos_x[n] += shift.x;
+                soa.pos_y[n] += shift.y;
+                shift_x.push(shift.x);
+                shift_y.push(shift.y);
+            }
+
+            // the particles of this cell are contiguous in `neighbors`
+            let offset = neighbors.iter().position(|&j| j == indices[0]).unwrap();
+            
+            for n in offset..offset + indices.len() {
+                let pos = Vec2 { x: soa.pos_x[n], y: soa.pos_y[n] };
+
+                let (density, near_density) = density_chunked(
+                    pos,
+                    &soa.pos_x,
+                    &soa.pos_y,
+                    Some(n),
+                    interaction_radius,
+                );
 
                 // compute pressure
                 let pressure = pressure_multiplier * (density - rest_density);
                 let near_pressure = near_pressure_multiplier * near_density;
 
-                let mut dpos = Vec2 { x: 0., y: 0. };
-
-                for &j in &neighbors {
-                    if i == j { continue; }
-
-                    let particle_i = self.particles[i];
-                    let particle_j = &mut self.particles[j];
-
-                    let diff = particle_j.pos - particle_i.pos;
-                    let dist = Vec2::dist(diff);
-                    let q = dist / interaction_radius;
-
-                    if q < 1. {
-                        let displacement = diff.normalize() * (pressure * (1. - q) + near_pressure * (1. - q) * (1. - q)) * dt * dt;
-                        particle_j.pos += displacement / 2.;
-                        dpos -= displacement / 2.;
-                    }
-                }
+                soa.density[n] = density;
+                soa.near_density[n] = near_density;
+                soa.pressure[n] = pressure;
+                soa.near_pressure[n] = near_pressure;
+
+                displacement_chunked(
+                    pos,
+                    &soa.pos_x,
+                    &soa.pos_y,
+                    Some(n),
+                    interaction_radius,
+                    pressure,
+                    near_pressure,
+                    dt,
+                    &mut displacement_x,
+                    &mut displacement_y,
+                );
+
+                let dpos = apply_displacement_chunked(
+                    &mut soa.pos_x,
+                    &mut soa.pos_y,
+                    &displacement_x,
+                    &displacement_y,
+                );
+
+                soa.pos_x[n] += dpos.x;
+                soa.pos_y[n] += dpos.y;
+            }
 
-                self.particles[i].pos += dpos;
+            for n in 0..soa.len() {
+                soa.pos_x[n] -= shift_x[n];
+                soa.pos_y[n] -= shift_y[n];
             }
+
+            soa.scatter(&mut self.particles, &neighbors);
         }
     }
 
     fn neighbors_from_cell(&self, cell: (i32, i32)) -> Vec<usize> {
-        let cells = [
-            (cell.0 - 1, cell.1 - 1),
-            (cell.0 + 0, cell.1 - 1),
-            (cell.0 + 1, cell.1 - 1),
-            (cell.0 - 1, cell.1 + 0),
-            (cell.0 + 0, cell.1 + 0),
-            (cell.0 + 1, cell.1 + 0),
-            (cell.0 - 1, cell.1 + 1),
-            (cell.0 + 0, cell.1 + 1),
-            (cell.0 + 1, cell.1 + 1),
-        ];
-
-        let mut res = vec![];
-        for cell in cells {
-            if let Some(indices) = self.cells.get(&cell) {
-                res.append(&mut indices.clone());
+        let cell_size = self.interaction_radius * T::from_f32(2.);
+        let xs = self.boundary_x.neighbor_cell_keys(cell.0, self.boundaries.min.x, self.boundaries.max.x, cell_size);
+        let ys = self.boundary_y.neighbor_cell_keys(cell.1, self.boundaries.min.y, self.boundaries.max.y, cell_size);
+
+        let mut keys = vec![];
+        for &y in &ys {
+            for &x in &xs {
+                keys.push((x, y));
             }
         }
 
-        res
+        self.cells.neighbors(&keys)
     }
 
-    fn get_cell_key(&self, position: Vec2) -> (i32, i32) {
-        (
-            (position.x / (self.interaction_radius * 2.)).floor() as i32,
-            (position.y / (self.interaction_radius * 2.)).floor() as i32,
-        )
+    fn get_cell_key(&self, position: Vec2<T>) -> (i32, i32) {
+        position.cell_key(self.interaction_radius * T::from_f32(2.))
     }
 
-    // make sure not to add the same particle multiple times
-    fn add_to_cell(&mut self, index: usize, cell_key: (i32, i32)) {
-        match self.cells.get_mut(&cell_key) {
-            Some(cell) => {
-                self.particles[index].cell_index = cell.len();
-                cell.push(index);
-            }
-            None => {
-                self.particles[index].cell_index = 0;
-                self.cells.insert(cell_key, vec![index]);
-            }
+    // puts every particle in the cell of its prev_pos, which is where
+    // `step` expects to find it. particles are added in the order of their
+    // cell_index, so each cell is in the same order as when they were saved.
+    fn restore_cells(&mut self) {
+        self.cells.clear();
+
+        let mut order: Vec<usize> = (0..self.particles.len()).collect();
+        order.sort_by_key(|&i| self.particles[i].cell_index);
+
+        for i in order {
+            let cell = self.get_cell_key(self.particles[i].prev_pos);
+            self.cells.add(&mut self.particles, i, cell);
         }
     }
+}
+
+// `Simulation::periodic_diff`, for where `self` is borrowed.
+fn periodic_diff<T: Float>(boundaries: Rect<T>, boundary_x: BoundaryMode, boundary_y: BoundaryMode, from: Vec2<T>, to: Vec2<T>) -> Vec2<T> {
+    let diff = to - from;
 
-    fn remove_from_cell(&mut self, index: usize, cell_key: (i32, i32)) {
-        let cell = self.cells.get_mut(&cell_key).expect("empty cell");
-        self.particles[cell[cell.len() - 1]].cell_index = self.particles[index].cell_index;
-        cell.swap_remove(self.particles[index].cell_index);
+    Vec2 {
+        x: boundary_x.wrap_diff(diff.x, boundaries.width()),
+        y: boundary_y.wrap_diff(diff.y, boundaries.height()),
     }
 }
 
-impl Default for Simulation {
-    fn default() -> Simulation {
+impl<T: Float> Default for Simulation<T> {
+    fn default() -> Simulation<T> {
         SimulationBuilder::default().build()
     }
 }
 
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize> serde::Serialize for Simulation<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Simulation::serialize(self, serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: Float + serde::Deserialize<'de>> serde::Deserialize<'de> for Simulation<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Simulation<T>, D::Error> {
+        let mut simulation = Simulation::deserialize(deserializer)?;
+        simulation.restore_cells();
+        Ok(simulation)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    // drops a single particle for a second and returns how far it is from
+    // where the integration puts it exactly: v_n = n g dt, y_n = y_0 + g dt² n (n + 1) / 2.
+    fn free_fall_error<T: Float>() -> f64 {
+        let mut simulation = SimulationBuilder::<T>::default()
+            .with_boundaries(Rect {
+                min: Vec2 { x: T::ZERO, y: T::ZERO },
+                max: Vec2 { x: T::from_f32(1000.), y: T::from_f32(1000.) },
+            })
+            .with_particles(vec![Particle::new(
+                Vec2 { x: T::from_f32(100.), y: T::from_f32(100.) },
+                Vec2 { x: T::ZERO, y: T::ZERO },
+            )])
+            .build();
+        simulation.init();
+
+        let dt = T::from_f64(1. / 60.);
+        for _ in 0..60 {
+            simulation.step(dt);
+        }
+
+        let expected = 100. + 196. * (1. / 60.) * (1. / 60.) * 60. * 61. / 2.;
+        (simulation.particles()[0].pos.y.to_f64() - expected).abs()
+    }
+
     #[test]
-    fn add_to_cell() {
-        let mut simulation = Simulation::default();
-        simulation.add_to_cell(0, (0, 0));
+    fn f64_simulation() {
+        let f32_error = free_fall_error::<f32>();
+        let f64_error = free_fall_error::<f64>();
+
+        assert!(f64_error < 1e-9);
+        assert!(f64_error < f32_error);
+    }
+
+    #[test]
+    fn densities_stored_on_particles() {
+        let particles: Vec<Particle> = (0..9)
+            .map(|i| Particle::new(Vec2 { x: 50. + (i % 3) as f32 * 5., y: 50. + (i / 3) as f32 * 5. }, Vec2 { x: 0., y: 0. }))
+            .collect();
+
+        let mut densities = vec![];
+        for kernel in [RelaxationKernel::Scalar, RelaxationKernel::Chunked] {
+            let mut simulation = SimulationBuilder::default()
+                .with_boundaries(Rect { min: Vec2 { x: 0., y: 0. }, max: Vec2 { x: 200., y: 200. } })
+                .with_kernel(kernel)
+                .with_particles(particles.clone())
+                .build();
+            simulation.init();
+            simulation.step(1. / 60.);
+
+            // the middle particle has the most neighbors
+            let middle = simulation.particles()[4];
+            assert!(middle.density > simulation.particles()[0].density);
+            assert!(middle.near_density > 0.);
+            assert_eq!(middle.pressure, simulation.pressure_multiplier * (middle.density - simulation.rest_density));
+            densities.push(middle.density);
+        }
+
+        assert!((densities[0] - densities[1]).abs() < 1e-4);
+    }
+
+    #[test]
+    fn densities_from_current_positions() {
+        let particles: Vec<Particle> = (0..9)
+            .map(|i| Particle::new(Vec2 { x: 50. + (i % 3) as f32 * 5., y: 50. + (i / 3) as f32 * 5. }, Vec2 { x: 0., y: 0. }))
+            .collect();
+        let mut simulation = SimulationBuilder::default()
+            .with_boundaries(Rect { min: Vec2 { x: 0., y: 0. }, max: Vec2 { x: 200., y: 200. } })
+            .with_particles(particles)
+            .build();
+        simulation.init();
+
+        // nothing is stored before the first step
+        let densities = simulation.densities();
+        assert_eq!(simulation.particles()[4].density, 0.);
+        assert!(densities[4].0 > densities[0].0);
+        assert!(densities[4].1 > 0.);
+    }
+
+    #[test]
+    fn chunked_small_periodic_domain() {
+        // 100 wide, less than 4 interaction radii
+        let particles: Vec<Particle> = (0..40)
+            .map(|i| Particle::new(Vec2 { x: 5. + (i % 10) as f32 * 10., y: 20. + (i / 10) as f32 * 10. }, Vec2 { x: 0., y: 0. }))
+            .collect();
+
+        let mut positions = vec![];
+        for kernel in [RelaxationKernel::Scalar, RelaxationKernel::Chunked] {
+            let mut simulation = SimulationBuilder::default()
+                .with_boundaries(Rect { min: Vec2 { x: 0., y: 0. }, max: Vec2 { x: 100., y: 200. } })
+                .with_boundary_modes(BoundaryMode::Periodic, BoundaryMode::Clamp)
+                .with_kernel(kernel)
+                .with_particles(particles.clone())
+                .build();
+            simulation.init();
+
+            for _ in 0..5 {
+                simulation.step(1. / 60.);
+            }
+            positions.push(simulation.particles().iter().map(|particle| particle.pos).collect::<Vec<_>>());
+        }
+
+        assert_eq!(positions[0], positions[1]);
+    }
+
+    fn boundary_test_simulation(boundary_x: BoundaryMode, particles: Vec<Particle>) -> Simulation {
+        let mut simulation = SimulationBuilder::default()
+            .with_boundaries(Rect {
+                min: Vec2 { x: 0., y: 0. },
+                max: Vec2 { x: 300., y: 100. },
+            })
+            .with_boundary_modes(boundary_x, BoundaryMode::Clamp)
+            .with_gravity(Vec2 { x: 0., y: 0. })
+            .with_particles(particles)
+            .build();
+        simulation.init();
+
+        simulation
+    }
+
+    #[test]
+    fn periodic_boundaries_wrap() {
+        let mut simulation = boundary_test_simulation(
+            BoundaryMode::Periodic,
+            vec![Particle::new(Vec2 { x: 299., y: 50. }, Vec2 { x: 120., y: 0. })],
+        );
+
+        simulation.step(1. / 60.);
+        simulation.step(1. / 60.);
+
+        let particle = simulation.particles()[0];
+        assert!(particle.pos.x > 0. && particle.pos.x < 10.);
+        assert!((particle.vel.x - 120.).abs() < 1e-2);
+    }
+
+    #[test]
+    fn periodic_neighbors_across_edge() {
+        let simulation = boundary_test_simulation(
+            BoundaryMode::Periodic,
+            vec![
+                Particle::new(Vec2 { x: 1., y: 50. }, Vec2 { x: 0., y: 0. }),
+                Particle::new(Vec2 { x: 299., y: 50. }, Vec2 { x: 0., y: 0. }),
+            ],
+        );
+
+        let cell = simulation.get_cell_key(simulation.particles()[0].pos);
+        assert!(simulation.neighbors_from_cell(cell).contains(&1));
+
+        let diff = simulation.periodic_diff(simulation.particles()[0].pos, simulation.particles()[1].pos);
+        assert!((diff.x + 2.).abs() < 1e-4);
+    }
+
+    #[test]
+    fn open_boundaries_remove_particles() {
+        let mut simulation = boundary_test_simulation(
+            BoundaryMode::Open,
+            vec![
+                Particle::new(Vec2 { x: 150., y: 50. }, Vec2 { x: 0., y: 0. }),
+                Particle::new(Vec2 { x: 299., y: 50. }, Vec2 { x: 600., y: 0. }),
+            ],
+        );
+
+        simulation.step(1. / 60.);
+        simulation.step(1. / 60.);
+
+        assert_eq!(simulation.particles().len(), 1);
+        assert_eq!(*simulation.cells.get(&(1, 0)).unwrap(), vec![0]);
+    }
+
+    #[test]
+    fn interaction_push() {
+        let mut simulation = boundary_test_simulation(
+            BoundaryMode::Clamp,
+            vec![Particle::new(Vec2 { x: 150., y: 50. }, Vec2 { x: 0., y: 0. })],
+        );
+
+        simulation.set_interaction(Some(Interaction {
+            kind: InteractionKind::Push,
+            center: Vec2 { x: 140., y: 50. },
+            radius: 50.,
+            strength: 1000.,
+            velocity: Vec2 { x: 0., y: 0. },
+        }));
+        simulation.step(1. / 60.);
+
+        assert!(simulation.particles()[0].vel.x > 0.);
+    }
+
+    #[test]
+    fn interaction_drag() {
+        let mut simulation = boundary_test_simulation(
+            BoundaryMode::Clamp,
+            vec![
+                Particle::new(Vec2 { x: 100., y: 50. }, Vec2 { x: 0., y: 0. }),
+                Particle::new(Vec2 { x: 110., y: 50. }, Vec2 { x: 0., y: 0. }),
+                Particle::new(Vec2 { x: 250., y: 50. }, Vec2 { x: 0., y: 0. }),
+            ],
+        );
+
+        let mut drag = Interaction {
+            kind: InteractionKind::Drag,
+            center: Vec2 { x: 100., y: 50. },
+            radius: 20.,
+            strength: 200.,
+            velocity: Vec2 { x: 60., y: 0. },
+        };
+
+        // the center moves 60 to the right over a second, then stops
+        for i in 0..120 {
+            simulation.set_interaction(Some(drag));
+            simulation.step(1. / 60.);
+
+            if i < 60 {
+                drag.center.x += 1.;
+            } else {
+                drag.velocity.x = 0.;
+            }
+        }
+
+        assert_eq!(simulation.grabbed().collect::<Vec<_>>(), vec![0, 1]);
+        assert!((simulation.particles()[0].pos.x - 160.).abs() < 5.);
+        assert!((simulation.particles()[1].pos.x - 170.).abs() < 5.);
+        assert_eq!(simulation.particles()[2].pos.x, 250.);
+
+        simulation.set_interaction(None);
+        assert_eq!(simulation.grabbed().count(), 0);
+    }
+
+    #[test]
+    fn add_and_remove_particles() {
+        let mut simulation = boundary_test_simulation(
+            BoundaryMode::Clamp,
+            vec![Particle::new(Vec2 { x: 150., y: 50. }, Vec2 { x: 0., y: 0. })],
+        );
+
+        simulation.add_particle(Particle::new(Vec2 { x: 20., y: 20. }, Vec2 { x: 0., y: 0. }));
+        assert_eq!(*simulation.cells.get(&(0, 0)).unwrap(), vec![1]);
+
+        assert_eq!(simulation.remove_particles_in(Vec2 { x: 150., y: 50. }, 5.), 1);
+        assert_eq!(simulation.particles().len(), 1);
         assert_eq!(*simulation.cells.get(&(0, 0)).unwrap(), vec![0]);
-        simulation.add_to_cell(1, (0, 0));
-        assert_eq!(*simulation.cells.get(&(0, 0)).unwrap(), vec![0, 1]);
+
+        simulation.step(1. / 60.);
     }
 
     #[test]
-    fn remove_from_cell() {
-        let mut simulation = Simulation::default();
-        simulation.cells.insert((0, 0), vec![0, 1, 2, 3]);
-        simulation.remove_from_cell(1, (0, 0));
-        assert_eq!(*simulation.cells.get(&(0, 0)).unwrap(), vec![0, 3, 2]);
-        simulation.remove_from_cell(3, (0, 0));
-        assert_eq!(*simulation.cells.get(&(0, 0)).unwrap(), vec![0, 2]);
+    fn remove_particles_from_moving_fluid() {
+        // spread over several cells, so particles cross into other cells
+        let particles = (0..100)
+            .map(|i| Particle::new(Vec2 { x: 5. + (i % 20) as f32 * 14., y: 5. + (i / 20) as f32 * 18. }, Vec2 { x: 600., y: 0. }))
+            .collect();
+        let mut simulation = boundary_test_simulation(BoundaryMode::Clamp, particles);
+
+        for _ in 0..3 {
+            simulation.step(1. / 60.);
+        }
+
+        assert!(simulation.remove_particles_in(Vec2 { x: 150., y: 50. }, 30.) > 0);
+
+        for _ in 0..10 {
+            simulation.step(1. / 60.);
+        }
+    }
+
+    #[test]
+    fn obstacles_and_emitters() {
+        let mut simulation = boundary_test_simulation(BoundaryMode::Clamp, vec![]);
+        simulation.obstacles.push(Obstacle::Rect(Rect {
+            min: Vec2 { x: 100., y: 0. },
+            max: Vec2 { x: 200., y: 100. },
+        }));
+        simulation.emitters.push(
+            Emitter::new(Vec2 { x: 20., y: 50. }, Vec2 { x: 300., y: 0. }, 60., 10.).with_material(2),
+        );
+
+        for _ in 0..30 {
+            simulation.step(1. / 60.);
+        }
+
+        assert_eq!(simulation.particles().len(), 30);
+        for particle in simulation.particles() {
+            assert_eq!(particle.material, 2);
+            assert!(particle.pos.x <= 100.);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_rebuilds_cells() {
+        let particles = (0..50)
+            .map(|i| Particle::new(Vec2 { x: 20. + (i % 10) as f32 * 25., y: 20. + (i / 10) as f32 * 15. }, Vec2 { x: 30., y: 0. }))
+            .collect();
+        let mut simulation = boundary_test_simulation(BoundaryMode::Periodic, particles);
+
+        for _ in 0..10 {
+            simulation.step(1. / 60.);
+        }
+
+        let json = serde_json::to_string(&simulation).unwrap();
+        let mut loaded: Simulation = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(loaded.time(), simulation.time());
+        assert_eq!(loaded.particles().len(), simulation.particles().len());
+
+        let sorted_cells = |simulation: &Simulation| {
+            let mut cells: Vec<_> = simulation.cells.iter()
+                .filter(|(_, indices)| !indices.is_empty())
+                .map(|(key, indices)| {
+                    let mut indices = indices.clone();
+                    indices.sort();
+                    (*key, indices)
+                })
+                .collect();
+            cells.sort();
+            cells
+        };
+        assert_eq!(sorted_cells(&loaded), sorted_cells(&simulation));
+
+        loaded.step(1. / 60.);
     }
 }
@@ -1,5 +1,7 @@
 // TODO: maybe its slow because of the rendering?
 
+use std::fs::File;
+use std::io::BufWriter;
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Instant;
@@ -10,6 +12,21 @@ use egui::*;
 
 use fluid_sim::*;
 
+mod editor;
+use editor::*;
+
+mod history;
+use history::*;
+
+mod gif_capture;
+use gif_capture::*;
+
+mod display;
+use display::*;
+
+mod camera;
+use camera::*;
+
 fn main() {
     let native_options = eframe::NativeOptions::default();
     let _ = eframe::run_native(
@@ -19,24 +36,87 @@ fn main() {
     );
 }
 
+// there is only ever one app, so the variants' size difference doesn't matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Clone)]
 enum MyEguiApp {
     Setup {
-        particle_num: usize,
-        spacing: f32,
-        positions: Vec<Pos2>,
-        offset: egui::Vec2,
+        editor: Editor,
         builder: SimulationBuilder,
+        scene_path: String,
+        // result of the last scene save or load
+        scene_status: String,
+        trajectory_path: String,
+        // result of the last trajectory read
+        replay_status: String,
+        camera: Camera,
     },
     Simulate {
         simulation: Arc<Mutex<Simulation>>,
-        // sender to stop simulation thread
-        stop_tx: mpsc::Sender<bool>,
+        // controls the simulation thread
+        commands: mpsc::Sender<Command>,
+        // mirrors the simulation thread's state
+        paused: bool,
+        time_scale: f32,
+        history: Arc<Mutex<History>>,
+        // the history frame shown while paused
+        frame: usize,
+        // whether the simulation was rewound to `frame` since the last step
+        scrubbed: bool,
         // what should the app revert to when simulation is stopped?
         revert_state: Box<MyEguiApp>,
+        tool: Tool,
+        tool_radius: f32,
+        tool_strength: f32,
+        // saved with the "checkpoint" button
+        checkpoint: Option<Snapshot>,
+        snapshot_path: String,
+        // result of the last snapshot write or read
+        snapshot_status: String,
+        trajectory_path: String,
+        quantize_trajectory: bool,
+        recording: bool,
+        // result of the last attempt to start recording
+        recording_status: String,
+        gif: GifCapture,
+        display: Display,
+        camera: Camera,
+    },
+    Replay {
+        trajectory: Trajectory,
+        // seconds since the first frame
+        playback_time: f64,
+        playing: bool,
+        revert_state: Box<MyEguiApp>,
+        camera: Camera,
     },
 }
 
+/// Sent from the UI to the simulation thread.
+#[derive(Debug)]
+enum Command {
+    Pause,
+    Resume,
+    /// Steps once while paused.
+    Step,
+    /// Simulated seconds per real second.
+    SetTimeScale(f32),
+    /// Records every step from now on, until `StopRecording`.
+    StartRecording(Box<TrajectoryRecorder<BufWriter<File>>>),
+    StopRecording,
+    Stop,
+}
+
+/// What happens when the simulation is clicked or dragged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tool {
+    Push,
+    Pull,
+    Drag,
+    Spawn,
+    Erase,
+}
+
 impl MyEguiApp {
     fn new(_cc: &eframe::CreationContext<'_>) -> Self {
         // Customize egui here with cc.egui_ctx.set_fonts and cc.egui_ctx.set_visuals.
@@ -44,11 +124,16 @@ impl MyEguiApp {
         // Use the cc.gl (a glow::Context) to create graphics shaders and buffers that you can use
         // for e.g. egui::PaintCallback.
         MyEguiApp::Setup {
-            particle_num: 280,
-            spacing: 7.,
-            positions: vec![],
-            offset: egui::Vec2 { x: 17., y: 17. },
-            builder: SimulationBuilder::default(),
+            editor: Editor::default(),
+            builder: SimulationBuilder::default().with_boundaries(fluid_sim::Rect {
+                min: fluid_sim::Vec2 { x: 0., y: 0. },
+                max: fluid_sim::Vec2 { x: 800., y: 600. },
+            }),
+            scene_path: "scene.json".to_string(),
+            scene_status: String::new(),
+            trajectory_path: "trajectory.gz".to_string(),
+            replay_status: String::new(),
+            camera: Camera::default(),
         }
     }
 }
@@ -59,19 +144,55 @@ impl eframe::App for MyEguiApp {
             use MyEguiApp::*;
 
             match self {
-                Setup {
-                    particle_num,
-                    spacing,
-                    positions,
-                    offset,
-                    builder,
-                } => {
-                    ui.heading("particles");
-                    
-                    ui.add(Slider::new(particle_num, 0..=800).text("particle num"));
-                    ui.add(Slider::new(spacing, 0.0..=100.).text("spacing"));
-                    ui.add(Slider::new(&mut offset.x, 0.0..=100.).text("x offset"));
-                    ui.add(Slider::new(&mut offset.y, 0.0..=100.).text("y offset"));
+                Setup { editor, builder, scene_path, scene_status, trajectory_path, replay_status, camera } => {
+                    editor.side_panel(ui);
+
+                    ui.heading("scene");
+
+                    ui.text_edit_singleline(scene_path);
+                    ui.horizontal(|ui| {
+                        if ui.button("save").clicked() {
+                            *scene_status = match editor.with_builder(builder).to_scene_file(&*scene_path) {
+                                Ok(()) => format!("saved {scene_path}"),
+                                Err(err) => err.to_string(),
+                            };
+                        }
+
+                        if ui.button("load").clicked() {
+                            *scene_status = match SimulationBuilder::from_scene_file(&*scene_path) {
+                                Ok(loaded) => {
+                                    editor.load(&loaded);
+                                    *builder = loaded;
+                                    camera.fit();
+                                    format!("loaded {scene_path}")
+                                }
+                                Err(err) => err.to_string(),
+                            };
+                        }
+                    });
+                    ui.label(&*scene_status);
+
+                    ui.heading("replay");
+
+                    ui.text_edit_singleline(trajectory_path);
+                    if ui.button("replay").clicked() {
+                        match Trajectory::read_file(&*trajectory_path) {
+                            Ok(trajectory) => {
+                                let revert_state = self.clone();
+
+                                *self = Replay {
+                                    trajectory,
+                                    playback_time: 0.,
+                                    playing: true,
+                                    revert_state: Box::new(revert_state),
+                                    camera: Camera::default(),
+                                };
+                                return;
+                            }
+                            Err(err) => *replay_status = err.to_string(),
+                        }
+                    }
+                    ui.label(&*replay_status);
 
                     ui.heading("simulation settings");
 
@@ -79,30 +200,53 @@ impl eframe::App for MyEguiApp {
                     ui.add(egui::Slider::new(&mut builder.pressure_multiplier, 0.0..=50.).text("pressure multiplier"));
                     ui.add(egui::Slider::new(&mut builder.near_pressure_multiplier, 0.0..=50.).text("near pressure multiplier"));
                     ui.add(egui::Slider::new(&mut builder.rest_density, 0.0..=50.).text("rest density"));
-                    ui.add(egui::Slider::new(&mut builder.gravity, 0.0..=1000.).text("gravity"));
+                    ui.add(egui::Slider::new(&mut builder.gravity.x, -1000.0..=1000.).text("gravity x"));
+                    ui.add(egui::Slider::new(&mut builder.gravity.y, -1000.0..=1000.).text("gravity y"));
+                    boundary_mode_combo(ui, "x boundary", &mut builder.boundary_x);
+                    boundary_mode_combo(ui, "y boundary", &mut builder.boundary_y);
+                    // the boundaries start at (0, 0)
+                    ui.add(egui::Slider::new(&mut builder.boundaries.max.x, 100.0..=4000.).logarithmic(true).text("width"));
+                    ui.add(egui::Slider::new(&mut builder.boundaries.max.y, 100.0..=4000.).logarithmic(true).text("height"));
+
+                    camera.side_panel(ui);
 
                     if ui.button("run").clicked() {
-                        *builder = builder.with_particles(
-                            positions
-                                .into_iter()
-                                .map(|pos| Particle::new(
-                                    fluid_sim::Vec2 { x: pos.x, y: pos.y },
-                                    fluid_sim::Vec2 { x: 0., y: 0. },
-                                ))
-                                .collect(),
-                        );
-
-                        let mut sim = builder.build();
+                        let mut sim = editor.with_builder(builder).build();
                         sim.init();
+                        // 10 seconds, one snapshot every 6 steps
+                        let mut history = History::new(100, 6);
+                        history.record(&sim);
+
                         let arc_simulation = Arc::new(Mutex::new(sim));
+                        let arc_history = Arc::new(Mutex::new(history));
 
-                        let (tx, rx) = mpsc::channel::<bool>();
+                        let (tx, rx) = mpsc::channel::<Command>();
+                        let trajectory_path = trajectory_path.clone();
+                        let camera = *camera;
                         let revert_state = self.clone();
 
                         *self = Simulate {
                             simulation: Arc::clone(&arc_simulation),
-                            stop_tx: tx,
+                            commands: tx,
+                            paused: false,
+                            time_scale: 1.,
+                            history: Arc::clone(&arc_history),
+                            frame: 0,
+                            scrubbed: false,
                             revert_state: Box::new(revert_state),
+                            tool: Tool::Push,
+                            tool_radius: 60.,
+                            tool_strength: 2000.,
+                            checkpoint: None,
+                            snapshot_path: "snapshot.bin".to_string(),
+                            snapshot_status: String::new(),
+                            trajectory_path,
+                            quantize_trajectory: false,
+                            recording: false,
+                            recording_status: String::new(),
+                            gif: GifCapture::default(),
+                            display: Display::default(),
+                            camera,
                         };
 
                         thread::spawn(move || {
@@ -112,51 +256,154 @@ impl eframe::App for MyEguiApp {
                             let mut now = Instant::now();
 
                             let mut accum = 0.;
-    
+                            let mut paused = false;
+                            let mut time_scale = 1.;
+                            let mut recorder: Option<Box<TrajectoryRecorder<_>>> = None;
+
                             'blk: loop {
-                                accum += now.elapsed().as_secs_f32();
+                                let mut steps = 0;
+
+                                loop {
+                                    match rx.try_recv() {
+                                        Ok(Command::Pause) => paused = true,
+                                        Ok(Command::Resume) => paused = false,
+                                        Ok(Command::Step) => steps += 1,
+                                        Ok(Command::SetTimeScale(scale)) => time_scale = scale,
+                                        Ok(Command::StartRecording(new_recorder)) => {
+                                            finish_recording(recorder.replace(new_recorder));
+                                        }
+                                        Ok(Command::StopRecording) => finish_recording(recorder.take()),
+                                        Ok(Command::Stop) | Err(mpsc::TryRecvError::Disconnected) => break 'blk,
+                                        Err(mpsc::TryRecvError::Empty) => break,
+                                    }
+                                }
+
+                                accum += now.elapsed().as_secs_f32() * time_scale;
                                 now = Instant::now();
 
-                                while accum >= dt {
-                                    let mut simulation = arc_simulation.lock().unwrap();
-                                    simulation.step(dt);
+                                if paused {
+                                    accum = 0.;
+                                }
 
+                                while accum >= dt {
+                                    steps += 1;
                                     accum -= dt;
                                 }
 
-                                match rx.try_recv() {
-                                    Ok(stop) if stop => break 'blk,
-                                    _ => (),
+                                for _ in 0..steps {
+                                    let mut simulation = arc_simulation.lock().unwrap();
+                                    simulation.step(dt);
+                                    arc_history.lock().unwrap().record(&simulation);
+
+                                    if let Some(Err(err)) = recorder.as_mut().map(|recorder| recorder.record(&simulation)) {
+                                        eprintln!("recording stopped: {err}");
+                                        recorder = None;
+                                    }
                                 }
                             }
 
+                            finish_recording(recorder);
+
                             println!("stopping simulation thread");
                         });
-
-                        return;
                     }
+                }
+                Simulate {
+                    simulation,
+                    commands,
+                    paused,
+                    time_scale,
+                    history,
+                    frame,
+                    scrubbed,
+                    revert_state,
+                    tool,
+                    tool_radius,
+                    tool_strength,
+                    checkpoint,
+                    snapshot_path,
+                    snapshot_status,
+                    trajectory_path,
+                    quantize_trajectory,
+                    recording,
+                    recording_status,
+                    gif,
+                    display,
+                    camera,
+                } => {
+                    ui.heading("mouse");
+
+                    ComboBox::from_label("tool")
+                        .selected_text(format!("{tool:?}"))
+                        .show_ui(ui, |ui| {
+                            for option in [Tool::Push, Tool::Pull, Tool::Drag, Tool::Spawn, Tool::Erase] {
+                                ui.selectable_value(tool, option, format!("{option:?}"));
+                            }
+                        });
+                    ui.add(egui::Slider::new(tool_radius, 5.0..=200.).text("tool radius"));
+                    ui.add(egui::Slider::new(tool_strength, 0.0..=10000.).text("tool strength"));
+
+                    ui.heading("timeline");
 
-                    *positions = vec![Pos2::ZERO; *particle_num];
+                    let mut history = history.lock().unwrap();
+                    let last_frame = history.len().saturating_sub(1);
+                    let mut scrubbed_to = None;
 
-                    let columns = f32::sqrt(*particle_num as f32) as u32;
+                    if *paused {
+                        // stepping moves the slider along with the history
+                        if !*scrubbed {
+                            *frame = last_frame;
+                        }
+
+                        let previous_frame = *frame;
+                        ui.add(egui::Slider::new(frame, 0..=last_frame).text("frame"));
+                        ui.label(format!("a frame is kept every {} steps", history.interval()));
 
-                    let mut x = 0;
-                    let mut y = 0;
-                    for i in 0..*particle_num {
-                        if x >= columns {
-                            x = 0;
-                            y += 1;
+                        if *frame != previous_frame {
+                            scrubbed_to = history.get(*frame).cloned();
+                            *scrubbed = true;
                         }
 
-                        positions[i] = pos2(
-                            x as f32 * *spacing + offset.x,
-                            y as f32 * *spacing + offset.y,
-                        );
+                        ui.horizontal(|ui| {
+                            // the frames after a rewound one are replaced by the new steps
+                            if ui.button("resume").clicked() {
+                                if *scrubbed {
+                                    history.resume_from(*frame);
+                                    *scrubbed = false;
+                                }
+                                commands.send(Command::Resume).unwrap();
+                                *paused = false;
+                            }
+
+                            if ui.button("step").clicked() {
+                                if *scrubbed {
+                                    history.resume_from(*frame);
+                                    *scrubbed = false;
+                                }
+                                commands.send(Command::Step).unwrap();
+                            }
+                        });
+                    } else {
+                        *frame = last_frame;
+                        ui.add_enabled(false, egui::Slider::new(frame, 0..=last_frame).text("frame"));
 
-                        x += 1;
+                        if ui.button("pause").clicked() {
+                            commands.send(Command::Pause).unwrap();
+                            *paused = true;
+                        }
                     }
-                }
-                Simulate { simulation, stop_tx, revert_state } => {
+
+                    if ui.add(egui::Slider::new(time_scale, 0.1..=4.).logarithmic(true).text("time scale")).changed() {
+                        commands.send(Command::SetTimeScale(*time_scale)).unwrap();
+                    }
+
+                    // the simulation thread locks the simulation before the history
+                    drop(history);
+
+                    if let Some(history_frame) = scrubbed_to {
+                        history_frame.restore(&mut simulation.lock().unwrap());
+                    }
+
                     ui.heading("simulation settings");
 
                     let mut simulation = simulation.lock().unwrap();
@@ -171,16 +418,120 @@ impl eframe::App for MyEguiApp {
                     ui.add(egui::Slider::new(&mut simulation.pressure_multiplier, 0.0..=50.).text("pressure multiplier"));
                     ui.add(egui::Slider::new(&mut simulation.near_pressure_multiplier, 0.0..=50.).text("near pressure multiplier"));
                     ui.add(egui::Slider::new(&mut simulation.rest_density, 0.0..=50.).text("rest density"));
-                    ui.add(egui::Slider::new(&mut simulation.gravity, 0.0..=1000.).text("gravity"));
+                    ui.add(egui::Slider::new(&mut simulation.gravity.x, -1000.0..=1000.).text("gravity x"));
+                    ui.add(egui::Slider::new(&mut simulation.gravity.y, -1000.0..=1000.).text("gravity y"));
+                    boundary_mode_combo(ui, "x boundary", &mut simulation.boundary_x);
+                    boundary_mode_combo(ui, "y boundary", &mut simulation.boundary_y);
+
+                    display.side_panel(ui);
+                    camera.side_panel(ui);
+
+                    ui.heading("snapshots");
+
+                    ui.horizontal(|ui| {
+                        if ui.button("checkpoint").clicked() {
+                            *checkpoint = Some(simulation.snapshot());
+                        }
+
+                        if let Some(checkpoint) = checkpoint {
+                            if ui.button(format!("back to {:.2}s", checkpoint.time)).clicked() {
+                                simulation.restore(checkpoint);
+                            }
+                        }
+                    });
+
+                    ui.text_edit_singleline(snapshot_path);
+                    ui.horizontal(|ui| {
+                        if ui.button("write").clicked() {
+                            *snapshot_status = match simulation.snapshot().write_file(&*snapshot_path) {
+                                Ok(()) => format!("wrote {snapshot_path}"),
+                                Err(err) => err.to_string(),
+                            };
+                        }
+
+                        if ui.button("read").clicked() {
+                            *snapshot_status = match Snapshot::read_file(&*snapshot_path) {
+                                Ok(snapshot) => {
+                                    simulation.restore(&snapshot);
+                                    format!("read {snapshot_path}")
+                                }
+                                Err(err) => err.to_string(),
+                            };
+                        }
+                    });
+                    ui.label(&*snapshot_status);
+
+                    ui.heading("recording");
+
+                    ui.text_edit_singleline(trajectory_path);
+                    ui.checkbox(quantize_trajectory, "quantize positions");
+
+                    if *recording {
+                        if ui.button("stop recording").clicked() {
+                            commands.send(Command::StopRecording).unwrap();
+                            *recording = false;
+                            *recording_status = format!("recorded {trajectory_path}");
+                        }
+                    } else if ui.button("record").clicked() {
+                        let encoding = match quantize_trajectory {
+                            true => TrajectoryEncoding::Quantized,
+                            false => TrajectoryEncoding::Full,
+                        };
+
+                        // every other step
+                        match TrajectoryRecorder::create(&*trajectory_path, simulation.boundaries, 2, encoding) {
+                            Ok(recorder) => {
+                                commands.send(Command::StartRecording(Box::new(recorder))).unwrap();
+                                *recording = true;
+                                *recording_status = format!("recording to {trajectory_path}");
+                            }
+                            Err(err) => *recording_status = err.to_string(),
+                        }
+                    }
+                    ui.label(&*recording_status);
 
                     drop(simulation);
 
+                    gif.side_panel(ui);
+
                     if ui.button("stop").clicked() {
-                        stop_tx.send(true).unwrap();
+                        commands.send(Command::Stop).unwrap();
                         *self = *revert_state.clone();
-                        return;
                     }
                 },
+                Replay { trajectory, playback_time, playing, revert_state, camera } => {
+                    ui.heading("replay");
+
+                    let duration = trajectory.frames.last().map_or(0., |frame| frame.time - trajectory.frames[0].time);
+
+                    ui.add(egui::Slider::new(playback_time, 0.0..=duration).text("time"));
+
+                    if *playing {
+                        *playback_time += ctx.input(|i| i.stable_dt) as f64;
+
+                        if *playback_time >= duration {
+                            *playback_time = duration;
+                            *playing = false;
+                        }
+
+                        if ui.button("pause").clicked() {
+                            *playing = false;
+                        }
+                    } else if ui.button("play").clicked() {
+                        if *playback_time >= duration {
+                            *playback_time = 0.;
+                        }
+                        *playing = true;
+                    }
+
+                    ui.label(format!("{} frames, one every {} steps", trajectory.frames.len(), trajectory.interval));
+
+                    camera.side_panel(ui);
+
+                    if ui.button("stop").clicked() {
+                        *self = *revert_state.clone();
+                    }
+                }
             }
         });
 
@@ -193,72 +544,173 @@ impl eframe::App for MyEguiApp {
         egui::Window::new("simulation").constrain_to(central_panel_rect).show(ctx, |ui| {
             use MyEguiApp::*;
 
-            let (response, painter) = ui.allocate_painter(
-                ui.available_size(),
-                Sense::empty(),
-            );
+            let (response, painter) = ui.allocate_painter(ui.available_size(), Sense::click_and_drag());
 
-            let painter_pos =
-                |pos: Pos2| pos2(pos.x + response.rect.min.x, pos.y + response.rect.min.y);
+            let (camera, boundaries) = match self {
+                Setup { camera, builder, .. } => (camera, builder.boundaries),
+                Simulate { camera, simulation, .. } => (camera, simulation.lock().unwrap().boundaries),
+                Replay { camera, trajectory, .. } => {
+                    let b = trajectory.boundaries;
+                    let boundaries = fluid_sim::Rect {
+                        min: fluid_sim::Vec2 { x: b.min.x as f32, y: b.min.y as f32 },
+                        max: fluid_sim::Vec2 { x: b.max.x as f32, y: b.max.y as f32 },
+                    };
+                    (camera, boundaries)
+                }
+            };
+            camera.update(&response, boundaries);
+            let camera = *camera;
 
             let interaction_diameter = match self {
                 Setup { builder, .. } => builder.interaction_radius,
                 Simulate { simulation, .. } => simulation.lock().unwrap().interaction_radius(),
+                // replays have no cells
+                Replay { .. } => 0.,
             } * 2.;
 
-            let mut x = 0;
-            let mut y = 0;
-            loop {
-                let display_pos = painter_pos(pos2(
-                    x as f32 * interaction_diameter,
-                    y as f32 * interaction_diameter,
-                ));
-
-                if display_pos.x > response.rect.max.x {
-                    x = 0;
-                    y += 1;
-                    continue;
+            // the cell grid, unless the cells would be too small to see
+            if interaction_diameter * camera.zoom > 4. {
+                let visible = camera.visible_rect();
+                let stroke = Stroke::new(1., Color32::GRAY);
+
+                for x in (visible.min.x / interaction_diameter).ceil() as i32..=(visible.max.x / interaction_diameter).floor() as i32 {
+                    let x = camera.world_to_screen(pos2(x as f32 * interaction_diameter, 0.)).x;
+                    painter.vline(x, response.rect.y_range(), stroke);
                 }
 
-                if display_pos.y > response.rect.max.y {
-                    break;
+                for y in (visible.min.y / interaction_diameter).ceil() as i32..=(visible.max.y / interaction_diameter).floor() as i32 {
+                    let y = camera.world_to_screen(pos2(0., y as f32 * interaction_diameter)).y;
+                    painter.hline(response.rect.x_range(), y, stroke);
                 }
-                
-                painter.rect_stroke(
-                    egui::Rect {
-                        min: pos2(display_pos.x, display_pos.y),
-                        max: pos2(display_pos.x + interaction_diameter, display_pos.y + interaction_diameter),
-                    },
-                    0.,
-                    Stroke::new(1., Color32::GRAY),
-                    StrokeKind::Middle,
-                );
-
-                x += 1;
             }
 
+            painter.rect_stroke(
+                egui::Rect::from_two_pos(
+                    camera.world_to_screen(pos2(boundaries.min.x, boundaries.min.y)),
+                    camera.world_to_screen(pos2(boundaries.max.x, boundaries.max.y)),
+                ),
+                0.,
+                Stroke::new(2., Color32::WHITE),
+                StrokeKind::Outside,
+            );
+
             match self {
-                Setup { positions, .. } => {
-                    for position in positions.iter().map(|pos| painter_pos(*pos)) {
-                        painter.circle_filled(position, 3., Color32::BLUE);
-                    }
+                Setup { editor, .. } => {
+                    editor.handle_input(&response, &camera);
+                    editor.draw(&painter, &response, &camera);
                 }
-                Simulate { simulation, .. } => {
+                Simulate { simulation, tool, tool_radius, tool_strength, gif, display, .. } => {
+                    gif.update(ctx, response.rect);
+
                     let mut simulation = simulation.lock().unwrap();
 
-                    simulation.boundaries = fluid_sim::Rect {
-                        min: fluid_sim::Vec2 { x: 0., y: 0. },
-                        max: fluid_sim::Vec2 { x: response.rect.width(), y: response.rect.height() },
+                    // the other buttons pan the camera
+                    let pointer = response
+                        .interact_pointer_pos()
+                        .filter(|_| response.is_pointer_button_down_on() && ctx.input(|i| i.pointer.primary_down()))
+                        .map(|pos| camera.screen_to_world(pos))
+                        .map(|pos| fluid_sim::Vec2 { x: pos.x, y: pos.y });
+
+                    let kind = match tool {
+                        Tool::Push => Some(InteractionKind::Push),
+                        Tool::Pull => Some(InteractionKind::Pull),
+                        Tool::Drag => Some(InteractionKind::Drag),
+                        Tool::Spawn | Tool::Erase => None,
+                    };
+
+                    let interaction = match (pointer, kind) {
+                        (Some(center), Some(kind)) => {
+                            let frame_dt = ctx.input(|i| i.stable_dt).max(1e-3);
+                            let delta = response.drag_delta() / frame_dt / camera.zoom;
+
+                            Some(Interaction {
+                                kind,
+                                center,
+                                radius: *tool_radius,
+                                strength: match kind {
+                                    // drag strength is a spring's stiffness, not an acceleration
+                                    InteractionKind::Drag => *tool_strength / 10.,
+                                    _ => *tool_strength,
+                                },
+                                velocity: fluid_sim::Vec2 { x: delta.x, y: delta.y },
+                            })
+                        }
+                        _ => None,
                     };
+                    simulation.set_interaction(interaction);
+
+                    if let Some(center) = pointer {
+                        match tool {
+                            Tool::Spawn => spawn_particles(&mut simulation, center, *tool_radius),
+                            Tool::Erase => { simulation.remove_particles_in(center, *tool_radius); }
+                            _ => (),
+                        }
+                    }
 
-                    for particle in simulation.particles() {
-                        let pos = painter_pos(pos2(particle.pos.x, particle.pos.y));
-                        painter.circle_filled(pos, 3., Color32::BLUE);
+                    if let Some(hover) = response.hover_pos() {
+                        painter.circle_stroke(hover, *tool_radius * camera.zoom, Stroke::new(1., Color32::WHITE));
                     }
 
+                    draw_obstacles(&painter, &simulation.obstacles, &camera);
+                    draw_emitters(&painter, &simulation.emitters, &camera);
+
+                    display.draw(&painter, &camera, &simulation);
+
                     ctx.request_repaint();
                 }
+                Replay { trajectory, playback_time, playing, .. } => {
+                    let start = trajectory.frames.first().map_or(0., |frame| frame.time);
+
+                    // the last frame at or before the playback time
+                    let frame = trajectory.frames
+                        .iter()
+                        .take_while(|frame| frame.time - start <= *playback_time)
+                        .last();
+
+                    if let Some(frame) = frame {
+                        for (pos, &material) in frame.positions.iter().zip(&frame.materials) {
+                            painter.circle_filled(camera.world_to_screen(pos2(pos.x, pos.y)), particle_radius(&camera), material_color(material));
+                        }
+                    }
+
+                    if *playing {
+                        ctx.request_repaint();
+                    }
+                }
             }
         });
     }
 }
+
+fn finish_recording(recorder: Option<Box<TrajectoryRecorder<BufWriter<File>>>>) {
+    if let Some(Err(err)) = recorder.map(|recorder| recorder.finish()) {
+        eprintln!("failed to finish recording: {err}");
+    }
+}
+
+fn boundary_mode_combo(ui: &mut Ui, label: &str, mode: &mut BoundaryMode) {
+    ComboBox::from_label(label)
+        .selected_text(format!("{mode:?}"))
+        .show_ui(ui, |ui| {
+            for option in [BoundaryMode::Clamp, BoundaryMode::Periodic, BoundaryMode::Open] {
+                ui.selectable_value(mode, option, format!("{option:?}"));
+            }
+        });
+}
+
+// adds a few particles spread over a disc around `center`.
+fn spawn_particles(simulation: &mut Simulation, center: fluid_sim::Vec2, radius: f32) {
+    const GOLDEN_ANGLE: f32 = 2.399_963;
+
+    for _ in 0..4 {
+        // a sunflower pattern, continued from wherever the particle count left it
+        let n = simulation.particles().len() as f32;
+        let angle = n * GOLDEN_ANGLE;
+        let distance = radius * (n * 0.618_034).fract().sqrt();
+
+        simulation.add_particle(Particle::new(
+            center + fluid_sim::Vec2 { x: angle.cos(), y: angle.sin() } * distance,
+            fluid_sim::Vec2 { x: 0., y: 0. },
+        ));
+    }
+}
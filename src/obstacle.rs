@@ -0,0 +1,60 @@
+use crate::*;
+
+/// A solid shape that particles can't enter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Obstacle<T = f32> {
+    Circle { center: Vec2<T>, radius: T },
+    Rect(Rect<T>),
+}
+
+impl<T: Float> Obstacle<T> {
+    pub fn contains(&self, pos: Vec2<T>) -> bool {
+        match *self {
+            Obstacle::Circle { center, radius } => (pos - center).dist_sqr() < radius * radius,
+            Obstacle::Rect(rect) => {
+                pos.x > rect.min.x && pos.x < rect.max.x && pos.y > rect.min.y && pos.y < rect.max.y
+            }
+        }
+    }
+
+    /// Returns the closest position to `pos` outside of the obstacle.
+    /// Positions already outside are returned unchanged.
+    pub fn push_out(&self, pos: Vec2<T>) -> Vec2<T> {
+        if !self.contains(pos) {
+            return pos;
+        }
+
+        match *self {
+            Obstacle::Circle { center, radius } => {
+                let diff = pos - center;
+
+                // a particle right at the center is pushed up
+                let normal = if diff.dist_sqr() == T::ZERO {
+                    Vec2 { x: T::ZERO, y: -T::ONE }
+                } else {
+                    diff.normalize()
+                };
+
+                center + normal * radius
+            }
+            Obstacle::Rect(rect) => {
+                let left = pos.x - rect.min.x;
+                let right = rect.max.x - pos.x;
+                let top = pos.y - rect.min.y;
+                let bottom = rect.max.y - pos.y;
+                let closest = left.min(right).min(top).min(bottom);
+
+                if closest == left {
+                    Vec2 { x: rect.min.x, y: pos.y }
+                } else if closest == right {
+                    Vec2 { x: rect.max.x, y: pos.y }
+                } else if closest == top {
+                    Vec2 { x: pos.x, y: rect.min.y }
+                } else {
+                    Vec2 { x: pos.x, y: rect.max.y }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,12 @@
+/// Runs `f` on every item, on rayon's thread pool when the `parallel`
+/// feature is enabled and one after another otherwise.
+pub(crate) fn for_each_mut<T: Send>(items: &mut [T], f: impl Fn(&mut T) + Send + Sync) {
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        items.par_iter_mut().for_each(f);
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    items.iter_mut().for_each(f);
+}
@@ -1,24 +1,53 @@
 use crate::*;
 
-#[derive(Debug, Clone, Copy)]
-pub struct Particle {
-    pub pos: Vec2,
-    pub vel: Vec2,
-    pub prev_pos: Vec2,
+/// A particle of `Simulation`, or of `Simulation3` with `V` being `Vec3`,
+/// see `Particle3`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Particle<T = f32, V = Vec2<T>> {
+    pub pos: V,
+    pub vel: V,
+    pub prev_pos: V,
     /// The particle's index in its current cell.
     pub cell_index: usize,
+    /// Which fluid the particle belongs to. Only used to tell fluids apart,
+    /// e.g. when drawing them.
+    pub material: u32,
+    /// The density computed in the last step's density relaxation, before
+    /// that step moved the particles. 0 until the first step, see
+    /// `Simulation::densities` for the current one.
+    pub density: T,
+    pub near_density: T,
+    /// The pressure computed from `density` in the last step.
+    pub pressure: T,
+    pub near_pressure: T,
+    /// Seconds simulated since the particle was added.
+    pub age: T,
 }
 
-impl Particle {
+impl<T: Float, V: Vector<T>> Particle<T, V> {
+    pub fn with_material(&self, material: u32) -> Particle<T, V> {
+        Particle {
+            material,
+            ..*self
+        }
+    }
+
     pub fn new(
-        pos: Vec2,
-        vel: Vec2,
-    ) -> Particle {
+        pos: V,
+        vel: V,
+    ) -> Particle<T, V> {
         Particle {
             pos,
             vel,
-            prev_pos: Vec2 { x: 0., y: 0. },
+            prev_pos: V::ZERO,
             cell_index: 0,
+            material: 0,
+            density: T::ZERO,
+            near_density: T::ZERO,
+            pressure: T::ZERO,
+            near_pressure: T::ZERO,
+            age: T::ZERO,
         }
     }
 }
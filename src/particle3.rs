@@ -0,0 +1,4 @@
+use crate::*;
+
+/// The 3D counterpart of `Particle`.
+pub type Particle3<T = f32> = Particle<T, Vec3<T>>;
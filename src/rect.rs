@@ -1,17 +1,18 @@
-use crate::Vec2;
+use crate::{Float, Vec2};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
-pub struct Rect {
-    pub min: Vec2,
-    pub max: Vec2,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rect<T = f32> {
+    pub min: Vec2<T>,
+    pub max: Vec2<T>,
 }
 
-impl Rect {
-    pub fn width(self) -> f32 {
+impl<T: Float> Rect<T> {
+    pub fn width(self) -> T {
         self.max.x - self.min.x
     }
 
-    pub fn height(self) -> f32 {
+    pub fn height(self) -> T {
         self.max.y - self.min.y
     }
 }
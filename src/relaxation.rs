@@ -0,0 +1,72 @@
+use crate::*;
+
+/// The double density relaxation, shared by `Simulation` and `Simulation3`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Relaxation<T> {
+    pub interaction_radius: T,
+    pub pressure_multiplier: T,
+    pub near_pressure_multiplier: T,
+    pub rest_density: T,
+}
+
+impl<T: Float> Relaxation<T> {
+    /// Relaxes the particles at `indices` one after another against
+    /// `neighbors`, storing their densities and pressures.
+    /// `offset(from, to)` returns the vector from `from` to `to`.
+    pub fn relax_cell<V: Vector<T>>(
+        &self,
+        particles: &mut [Particle<T, V>],
+        indices: &[usize],
+        neighbors: &[usize],
+        dt: T,
+        offset: impl Fn(V, V) -> V,
+    ) {
+        let two = T::from_f32(2.);
+
+        for &i in indices {
+            let mut density = T::ZERO;
+            let mut near_density = T::ZERO;
+
+            // compute density
+            for &j in neighbors {
+                if i == j { continue; }
+
+                let dist = offset(particles[j].pos, particles[i].pos).dist();
+                let q = dist / self.interaction_radius;
+
+                if q < T::ONE {
+                    density += (T::ONE - q) * (T::ONE - q);
+                    near_density += (T::ONE - q) * (T::ONE - q) * (T::ONE - q);
+                }
+            }
+
+            // compute pressure
+            let pressure = self.pressure_multiplier * (density - self.rest_density);
+            let near_pressure = self.near_pressure_multiplier * near_density;
+
+            let particle_i = &mut particles[i];
+            particle_i.density = density;
+            particle_i.near_density = near_density;
+            particle_i.pressure = pressure;
+            particle_i.near_pressure = near_pressure;
+
+            let mut dpos = V::ZERO;
+
+            for &j in neighbors {
+                if i == j { continue; }
+
+                let diff = offset(particles[i].pos, particles[j].pos);
+                let dist = diff.dist();
+                let q = dist / self.interaction_radius;
+
+                if q < T::ONE {
+                    let displacement = diff.normalize() * (pressure * (T::ONE - q) + near_pressure * (T::ONE - q) * (T::ONE - q)) * dt * dt;
+                    particles[j].pos += displacement / two;
+                    dpos -= displacement / two;
+                }
+            }
+
+            particles[i].pos += dpos;
+        }
+    }
+}
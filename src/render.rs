@@ -0,0 +1,375 @@
+//! A software rasterizer for rendering frames without a window or GPU,
+//! e.g. to make videos of headless runs.
+
+#[cfg(feature = "png")]
+use std::fs::File;
+#[cfg(feature = "png")]
+use std::io::{self, BufWriter, Write};
+#[cfg(feature = "png")]
+use std::path::Path;
+
+use crate::*;
+
+/// Non-premultiplied RGBA.
+pub type Color = [u8; 4];
+
+/// The colors particles are drawn with, indexed by `material`.
+pub const MATERIAL_PALETTE: [Color; 8] = [
+    [0, 0, 255, 255],
+    [230, 80, 60, 255],
+    [60, 200, 90, 255],
+    [240, 200, 40, 255],
+    [180, 80, 220, 255],
+    [40, 200, 220, 255],
+    [240, 140, 40, 255],
+    [220, 220, 220, 255],
+];
+
+pub fn material_palette_color(material: u32) -> Color {
+    MATERIAL_PALETTE[material as usize % MATERIAL_PALETTE.len()]
+}
+
+/// An RGBA image, row by row from the top left.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<Color>,
+}
+
+impl Image {
+    pub fn new(width: u32, height: u32, background: Color) -> Image {
+        Image {
+            width,
+            height,
+            pixels: vec![background; width as usize * height as usize],
+        }
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> Color {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    /// Draws `color` over the pixel, `coverage` being how much of the pixel
+    /// it covers from 0 to 1.
+    pub fn blend(&mut self, x: u32, y: u32, color: Color, coverage: f64) {
+        let alpha = coverage.clamp(0., 1.) * color[3] as f64 / 255.;
+        let pixel = &mut self.pixels[(y * self.width + x) as usize];
+        let pixel_alpha = pixel[3] as f64 / 255.;
+        let out_alpha = alpha + pixel_alpha * (1. - alpha);
+
+        if out_alpha <= 0. {
+            return;
+        }
+
+        // the colors aren't premultiplied, so they're weighted by their alpha
+        for c in 0..3 {
+            let blended = color[c] as f64 * alpha + pixel[c] as f64 * pixel_alpha * (1. - alpha);
+            pixel[c] = (blended / out_alpha).round() as u8;
+        }
+        pixel[3] = (out_alpha * 255.).round() as u8;
+    }
+
+    #[cfg(feature = "png")]
+    pub fn write_png(&self, writer: impl Write) -> io::Result<()> {
+        let mut encoder = png::Encoder::new(writer, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        writer.write_image_data(self.pixels.as_flattened()).map_err(io::Error::other)?;
+        writer.finish().map_err(io::Error::other)
+    }
+
+    #[cfg(feature = "png")]
+    pub fn write_png_file(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.write_png(BufWriter::new(File::create(path)?))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParticleStyle {
+    /// Anti-aliased discs of `radius` pixels.
+    Discs { radius: f64 },
+    /// A smooth surface around the particles. Each particle adds to a field
+    /// within `radius` pixels, and pixels where the field is above
+    /// `threshold` are filled.
+    Metaballs { radius: f64, threshold: f64 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderOptions {
+    /// Pixels per simulation unit.
+    pub scale: f64,
+    pub background: Color,
+    pub particle_style: ParticleStyle,
+    /// Drawn in `obstacle_color` if set.
+    pub obstacle_color: Option<Color>,
+    /// Draws the cells' outlines in `grid_color` if set.
+    pub grid_color: Option<Color>,
+}
+
+impl RenderOptions {
+    pub fn with_scale(&self, scale: f64) -> RenderOptions {
+        RenderOptions {
+            scale,
+            ..self.clone()
+        }
+    }
+
+    pub fn with_background(&self, background: Color) -> RenderOptions {
+        RenderOptions {
+            background,
+            ..self.clone()
+        }
+    }
+
+    pub fn with_particle_style(&self, particle_style: ParticleStyle) -> RenderOptions {
+        RenderOptions {
+            particle_style,
+            ..self.clone()
+        }
+    }
+
+    pub fn with_obstacle_color(&self, obstacle_color: Option<Color>) -> RenderOptions {
+        RenderOptions {
+            obstacle_color,
+            ..self.clone()
+        }
+    }
+
+    pub fn with_grid_color(&self, grid_color: Option<Color>) -> RenderOptions {
+        RenderOptions {
+            grid_color,
+            ..self.clone()
+        }
+    }
+}
+
+impl Default for RenderOptions {
+    fn default() -> RenderOptions {
+        RenderOptions {
+            scale: 1.,
+            background: [0, 0, 0, 255],
+            particle_style: ParticleStyle::Discs { radius: 3. },
+            obstacle_color: Some([90, 90, 90, 255]),
+            grid_color: None,
+        }
+    }
+}
+
+/// Renders the simulation's `boundaries`, with `boundaries.min` at the top left.
+pub fn render<T: Float>(simulation: &Simulation<T>, options: &RenderOptions) -> Image {
+    let min = Vec2 { x: simulation.boundaries.min.x.to_f64(), y: simulation.boundaries.min.y.to_f64() };
+    let scale = options.scale;
+    let width = (simulation.boundaries.width().to_f64() * scale).ceil().max(1.) as u32;
+    let height = (simulation.boundaries.height().to_f64() * scale).ceil().max(1.) as u32;
+
+    let mut image = Image::new(width, height, options.background);
+
+    // simulation coordinates of a pixel's center
+    let to_sim = |x: u32, y: u32| Vec2 {
+        x: T::from_f64(min.x + (x as f64 + 0.5) / scale),
+        y: T::from_f64(min.y + (y as f64 + 0.5) / scale),
+    };
+
+    if let Some(color) = options.grid_color {
+        let cell_size = simulation.interaction_radius().to_f64() * 2. * scale;
+        // cell (0, 0) starts at (0, 0), which isn't always `min`
+        let offset_x = (-min.x * scale).rem_euclid(cell_size);
+        let offset_y = (-min.y * scale).rem_euclid(cell_size);
+
+        for y in 0..height {
+            for x in 0..width {
+                let on_x = ((x as f64 - offset_x).rem_euclid(cell_size)) < 1.;
+                let on_y = ((y as f64 - offset_y).rem_euclid(cell_size)) < 1.;
+
+                if on_x || on_y {
+                    image.blend(x, y, color, 1.);
+                }
+            }
+        }
+    }
+
+    if let Some(color) = options.obstacle_color {
+        if !simulation.obstacles.is_empty() {
+            for y in 0..height {
+                for x in 0..width {
+                    let pos = to_sim(x, y);
+
+                    if simulation.obstacles.iter().any(|obstacle| obstacle.contains(pos)) {
+                        image.blend(x, y, color, 1.);
+                    }
+                }
+            }
+        }
+    }
+
+    // particle positions in pixels
+    let particles: Vec<(Vec2<f64>, Color)> = simulation
+        .particles()
+        .iter()
+        .map(|particle| {
+            let pos = Vec2 {
+                x: (particle.pos.x.to_f64() - min.x) * scale,
+                y: (particle.pos.y.to_f64() - min.y) * scale,
+            };
+            (pos, material_palette_color(particle.material))
+        })
+        .collect();
+
+    match options.particle_style {
+        ParticleStyle::Discs { radius } => draw_discs(&mut image, &particles, radius),
+        ParticleStyle::Metaballs { radius, threshold } => draw_metaballs(&mut image, &particles, radius, threshold, 0.1),
+    }
+
+    image
+}
+
+// calls `f` with every pixel within `radius` of `center` and its distance to `center`.
+fn for_each_pixel_near(image: &Image, center: Vec2<f64>, radius: f64, mut f: impl FnMut(u32, u32, f64)) {
+    let min_x = (center.x - radius).floor().max(0.) as u32;
+    let min_y = (center.y - radius).floor().max(0.) as u32;
+    let max_x = (center.x + radius).ceil().min(image.width as f64 - 1.);
+    let max_y = (center.y + radius).ceil().min(image.height as f64 - 1.);
+
+    if max_x < 0. || max_y < 0. {
+        return;
+    }
+
+    for y in min_y..=max_y as u32 {
+        for x in min_x..=max_x as u32 {
+            let dist = Vec2::dist(Vec2 { x: x as f64 + 0.5, y: y as f64 + 0.5 } - center);
+
+            if dist <= radius {
+                f(x, y, dist);
+            }
+        }
+    }
+}
+
+/// Draws `particles` (positions in pixels) as anti-aliased discs of `radius` pixels.
+pub fn draw_discs(image: &mut Image, particles: &[(Vec2<f64>, Color)], radius: f64) {
+    for &(pos, color) in particles {
+        let mut covered = vec![];
+        // half a pixel of anti-aliasing at the edge
+        for_each_pixel_near(image, pos, radius + 0.5, |x, y, dist| covered.push((x, y, radius + 0.5 - dist)));
+
+        for (x, y, coverage) in covered {
+            image.blend(x, y, color, coverage);
+        }
+    }
+}
+
+/// Draws `particles` (positions in pixels) as a smooth surface, see
+/// `ParticleStyle::Metaballs`. The edge fades out where the field is within
+/// `softness * threshold` of `threshold`.
+pub fn draw_metaballs(image: &mut Image, particles: &[(Vec2<f64>, Color)], radius: f64, threshold: f64, softness: f64) {
+    let len = image.pixels.len();
+    let mut field = vec![0.; len];
+    // color sums weighted by each particle's contribution
+    let mut colors = vec![[0.; 3]; len];
+
+    for &(pos, color) in particles {
+        for_each_pixel_near(image, pos, radius, |x, y, dist| {
+            let q = 1. - (dist / radius) * (dist / radius);
+            let weight = q * q;
+            let i = (y * image.width + x) as usize;
+
+            field[i] += weight;
+            for c in 0..3 {
+                colors[i][c] += color[c] as f64 * weight;
+            }
+        });
+    }
+
+    for i in 0..len {
+        if field[i] <= 0. {
+            continue;
+        }
+
+        // anti-aliased edge where the field crosses the threshold
+        let coverage = if softness > 0. {
+            smoothstep(threshold * (1. - softness), threshold * (1. + softness), field[i])
+        } else if field[i] >= threshold {
+            1.
+        } else {
+            0.
+        };
+
+        if coverage > 0. {
+            let color = [
+                (colors[i][0] / field[i]).round() as u8,
+                (colors[i][1] / field[i]).round() as u8,
+                (colors[i][2] / field[i]).round() as u8,
+                255,
+            ];
+            let (x, y) = (i as u32 % image.width, i as u32 / image.width);
+            image.blend(x, y, color, coverage);
+        }
+    }
+}
+
+fn smoothstep(from: f64, to: f64, value: f64) -> f64 {
+    let t = ((value - from) / (to - from)).clamp(0., 1.);
+    t * t * (3. - 2. * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_simulation() -> Simulation {
+        let mut simulation = SimulationBuilder::default()
+            .with_boundaries(Rect { min: Vec2 { x: 0., y: 0. }, max: Vec2 { x: 100., y: 50. } })
+            .with_obstacle(Obstacle::Rect(Rect { min: Vec2 { x: 80., y: 0. }, max: Vec2 { x: 100., y: 50. } }))
+            .with_particles(vec![
+                Particle::new(Vec2 { x: 20., y: 20. }, Vec2 { x: 0., y: 0. }),
+                Particle::new(Vec2 { x: 26., y: 20. }, Vec2 { x: 0., y: 0. }).with_material(1),
+            ])
+            .build();
+        simulation.init();
+        simulation
+    }
+
+    #[test]
+    fn discs_and_obstacles() {
+        let image = render(&test_simulation(), &RenderOptions::default().with_scale(2.));
+
+        assert_eq!((image.width, image.height), (200, 100));
+        assert_eq!(image.get(40, 40), MATERIAL_PALETTE[0]);
+        assert_eq!(image.get(52, 40), MATERIAL_PALETTE[1]);
+        assert_eq!(image.get(46, 40), [0, 0, 0, 255]);
+        assert_eq!(image.get(190, 10), [90, 90, 90, 255]);
+    }
+
+    #[test]
+    fn metaballs_merge() {
+        let options = RenderOptions::default()
+            .with_particle_style(ParticleStyle::Metaballs { radius: 8., threshold: 0.5 });
+        let image = render(&test_simulation(), &options);
+
+        // between the two particles, which discs would leave empty
+        let between = image.get(23, 20);
+        assert_ne!(between, [0, 0, 0, 255]);
+        assert_eq!(image.get(50, 20), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn blend_over_transparent() {
+        let mut image = Image::new(1, 1, [0, 0, 0, 0]);
+        image.blend(0, 0, [200, 100, 50, 255], 0.5);
+
+        // not darkened by the transparent black underneath
+        assert_eq!(image.get(0, 0), [200, 100, 50, 128]);
+    }
+
+    #[test]
+    #[cfg(feature = "png")]
+    fn png() {
+        let mut bytes = vec![];
+        render(&test_simulation(), &RenderOptions::default()).write_png(&mut bytes).unwrap();
+
+        assert_eq!(&bytes[1..4], b"PNG");
+    }
+}
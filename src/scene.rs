@@ -0,0 +1,432 @@
+//! A versioned JSON scene format, so setups can be shared as files.
+//!
+//! Scenes store numbers as `f64` regardless of the simulation's float type.
+//! Force fields are not stored.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::*;
+
+/// The scene format version written by `to_scene_file`.
+pub const SCENE_VERSION: u32 = 1;
+
+/// The most particles a single `SceneBlock` can be filled with.
+pub const MAX_BLOCK_PARTICLES: usize = 1_000_000;
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    /// The scene was written by a newer version of this crate.
+    UnsupportedVersion(u32),
+    /// A block's lattice has more than `MAX_BLOCK_PARTICLES` points, e.g.
+    /// because its spacing is tiny.
+    TooManyParticles(f64),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(err) => write!(f, "scene io error: {err}"),
+            SceneError::Json(err) => write!(f, "invalid scene: {err}"),
+            SceneError::UnsupportedVersion(version) => {
+                write!(f, "unsupported scene version {version}, expected at most {SCENE_VERSION}")
+            }
+            SceneError::TooManyParticles(count) => {
+                write!(f, "a block would have {count} particles, expected at most {MAX_BLOCK_PARTICLES}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<io::Error> for SceneError {
+    fn from(err: io::Error) -> SceneError {
+        SceneError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for SceneError {
+    fn from(err: serde_json::Error) -> SceneError {
+        SceneError::Json(err)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Scene {
+    pub version: u32,
+    pub parameters: SceneParameters,
+    /// Shapes filled with particles when the scene is loaded.
+    #[serde(default)]
+    pub blocks: Vec<SceneBlock>,
+    #[serde(default)]
+    pub particles: Vec<SceneParticle>,
+    #[serde(default)]
+    pub obstacles: Vec<SceneObstacle>,
+    #[serde(default)]
+    pub emitters: Vec<SceneEmitter>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneParameters {
+    pub gravity: [f64; 2],
+    /// `[[min x, min y], [max x, max y]]`
+    pub boundaries: [[f64; 2]; 2],
+    #[serde(default)]
+    pub boundary_x: SceneBoundaryMode,
+    #[serde(default)]
+    pub boundary_y: SceneBoundaryMode,
+    pub interaction_radius: f64,
+    pub pressure_multiplier: f64,
+    pub near_pressure_multiplier: f64,
+    pub rest_density: f64,
+    #[serde(default)]
+    pub kernel: SceneKernel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SceneBoundaryMode {
+    #[default]
+    Clamp,
+    Periodic,
+    Open,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SceneKernel {
+    #[default]
+    Scalar,
+    Chunked,
+}
+
+/// A shape filled with particles on a `spacing` sized lattice.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneBlock {
+    pub shape: SceneShape,
+    pub spacing: f64,
+    #[serde(default)]
+    pub velocity: [f64; 2],
+    #[serde(default)]
+    pub material: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SceneShape {
+    Rect { min: [f64; 2], max: [f64; 2] },
+    Circle { center: [f64; 2], radius: f64 },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneParticle {
+    pub pos: [f64; 2],
+    #[serde(default)]
+    pub vel: [f64; 2],
+    #[serde(default)]
+    pub material: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneObstacle {
+    pub shape: SceneShape,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneEmitter {
+    pub pos: [f64; 2],
+    pub velocity: [f64; 2],
+    pub rate: f64,
+    #[serde(default)]
+    pub width: f64,
+    #[serde(default)]
+    pub material: u32,
+}
+
+impl Scene {
+    pub fn from_json(json: &str) -> Result<Scene, SceneError> {
+        let scene: Scene = serde_json::from_str(json)?;
+
+        if scene.version > SCENE_VERSION {
+            return Err(SceneError::UnsupportedVersion(scene.version));
+        }
+
+        Ok(scene)
+    }
+
+    pub fn to_json(&self) -> Result<String, SceneError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+impl<T: Float> SimulationBuilder<T> {
+    pub fn from_scene(scene: &Scene) -> Result<SimulationBuilder<T>, SceneError> {
+        let p = &scene.parameters;
+
+        let mut particles: Vec<Particle<T>> = scene
+            .particles
+            .iter()
+            .map(|particle| Particle::new(vec2(particle.pos), vec2(particle.vel)).with_material(particle.material))
+            .collect();
+
+        for block in &scene.blocks {
+            particles.extend(block_particles(block)?);
+        }
+
+        Ok(SimulationBuilder {
+            gravity: vec2(p.gravity),
+            boundaries: Rect { min: vec2(p.boundaries[0]), max: vec2(p.boundaries[1]) },
+            boundary_x: p.boundary_x.into(),
+            boundary_y: p.boundary_y.into(),
+            interaction_radius: T::from_f64(p.interaction_radius),
+            pressure_multiplier: T::from_f64(p.pressure_multiplier),
+            near_pressure_multiplier: T::from_f64(p.near_pressure_multiplier),
+            rest_density: T::from_f64(p.rest_density),
+            kernel: p.kernel.into(),
+            particles,
+            obstacles: scene.obstacles.iter().map(|obstacle| obstacle.shape.to_obstacle()).collect(),
+            emitters: scene
+                .emitters
+                .iter()
+                .map(|emitter| {
+                    Emitter::new(
+                        vec2(emitter.pos),
+                        vec2(emitter.velocity),
+                        T::from_f64(emitter.rate),
+                        T::from_f64(emitter.width),
+                    )
+                    .with_material(emitter.material)
+                })
+                .collect(),
+            ..SimulationBuilder::default()
+        })
+    }
+
+    /// Particles are always written out one by one.
+    pub fn to_scene(&self) -> Scene {
+        Scene {
+            version: SCENE_VERSION,
+            parameters: SceneParameters {
+                gravity: array(self.gravity),
+                boundaries: [array(self.boundaries.min), array(self.boundaries.max)],
+                boundary_x: self.boundary_x.into(),
+                boundary_y: self.boundary_y.into(),
+                interaction_radius: self.interaction_radius.to_f64(),
+                pressure_multiplier: self.pressure_multiplier.to_f64(),
+                near_pressure_multiplier: self.near_pressure_multiplier.to_f64(),
+                rest_density: self.rest_density.to_f64(),
+                kernel: self.kernel.into(),
+            },
+            blocks: vec![],
+            particles: self
+                .particles
+                .iter()
+                .map(|particle| SceneParticle {
+                    pos: array(particle.pos),
+                    vel: array(particle.vel),
+                    material: particle.material,
+                })
+                .collect(),
+            obstacles: self
+                .obstacles
+                .iter()
+                .map(|obstacle| SceneObstacle { shape: SceneShape::from_obstacle(obstacle) })
+                .collect(),
+            emitters: self
+                .emitters
+                .iter()
+                .map(|emitter| SceneEmitter {
+                    pos: array(emitter.pos),
+                    velocity: array(emitter.velocity),
+                    rate: emitter.rate.to_f64(),
+                    width: emitter.width.to_f64(),
+                    material: emitter.material,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn from_scene_file(path: impl AsRef<Path>) -> Result<SimulationBuilder<T>, SceneError> {
+        let scene = Scene::from_json(&fs::read_to_string(path)?)?;
+        SimulationBuilder::from_scene(&scene)
+    }
+
+    pub fn to_scene_file(&self, path: impl AsRef<Path>) -> Result<(), SceneError> {
+        fs::write(path, self.to_scene().to_json()?)?;
+        Ok(())
+    }
+}
+
+impl SceneShape {
+    fn from_obstacle<T: Float>(obstacle: &Obstacle<T>) -> SceneShape {
+        match *obstacle {
+            Obstacle::Circle { center, radius } => SceneShape::Circle {
+                center: array(center),
+                radius: radius.to_f64(),
+            },
+            Obstacle::Rect(rect) => SceneShape::Rect {
+                min: array(rect.min),
+                max: array(rect.max),
+            },
+        }
+    }
+
+    fn to_obstacle<T: Float>(&self) -> Obstacle<T> {
+        match *self {
+            SceneShape::Circle { center, radius } => Obstacle::Circle {
+                center: vec2(center),
+                radius: T::from_f64(radius),
+            },
+            SceneShape::Rect { min, max } => Obstacle::Rect(Rect { min: vec2(min), max: vec2(max) }),
+        }
+    }
+}
+
+impl From<SceneBoundaryMode> for BoundaryMode {
+    fn from(mode: SceneBoundaryMode) -> BoundaryMode {
+        match mode {
+            SceneBoundaryMode::Clamp => BoundaryMode::Clamp,
+            SceneBoundaryMode::Periodic => BoundaryMode::Periodic,
+            SceneBoundaryMode::Open => BoundaryMode::Open,
+        }
+    }
+}
+
+impl From<BoundaryMode> for SceneBoundaryMode {
+    fn from(mode: BoundaryMode) -> SceneBoundaryMode {
+        match mode {
+            BoundaryMode::Clamp => SceneBoundaryMode::Clamp,
+            BoundaryMode::Periodic => SceneBoundaryMode::Periodic,
+            BoundaryMode::Open => SceneBoundaryMode::Open,
+        }
+    }
+}
+
+impl From<SceneKernel> for RelaxationKernel {
+    fn from(kernel: SceneKernel) -> RelaxationKernel {
+        match kernel {
+            SceneKernel::Scalar => RelaxationKernel::Scalar,
+            SceneKernel::Chunked => RelaxationKernel::Chunked,
+        }
+    }
+}
+
+impl From<RelaxationKernel> for SceneKernel {
+    fn from(kernel: RelaxationKernel) -> SceneKernel {
+        match kernel {
+            RelaxationKernel::Scalar => SceneKernel::Scalar,
+            RelaxationKernel::Chunked => SceneKernel::Chunked,
+        }
+    }
+}
+
+fn block_particles<T: Float>(block: &SceneBlock) -> Result<Vec<Particle<T>>, SceneError> {
+    let (min, max) = match block.shape {
+        SceneShape::Rect { min, max } => (min, max),
+        SceneShape::Circle { center, radius } => (
+            [center[0] - radius, center[1] - radius],
+            [center[0] + radius, center[1] + radius],
+        ),
+    };
+
+    let contains = |x: f64, y: f64| match block.shape {
+        SceneShape::Rect { .. } => true,
+        SceneShape::Circle { center, radius } => {
+            (x - center[0]).powi(2) + (y - center[1]).powi(2) <= radius * radius
+        }
+    };
+
+    let mut particles = vec![];
+    if block.spacing <= 0. {
+        return Ok(particles);
+    }
+
+    // the number of lattice points along each axis
+    let count = |min: f64, max: f64| if max >= min { ((max - min) / block.spacing).floor() + 1. } else { 0. };
+    let (columns, rows) = (count(min[0], max[0]), count(min[1], max[1]));
+
+    // also catches NaN and infinite bounds
+    if !(0. ..=MAX_BLOCK_PARTICLES as f64).contains(&(columns * rows)) {
+        return Err(SceneError::TooManyParticles(columns * rows));
+    }
+
+    for row in 0..rows as usize {
+        let y = min[1] + row as f64 * block.spacing;
+
+        for column in 0..columns as usize {
+            let x = min[0] + column as f64 * block.spacing;
+
+            if contains(x, y) {
+                particles.push(Particle::new(vec2([x, y]), vec2(block.velocity)).with_material(block.material));
+            }
+        }
+    }
+
+    Ok(particles)
+}
+
+fn vec2<T: Float>(array: [f64; 2]) -> Vec2<T> {
+    Vec2 { x: T::from_f64(array[0]), y: T::from_f64(array[1]) }
+}
+
+fn array<T: Float>(vec: Vec2<T>) -> [f64; 2] {
+    [vec.x.to_f64(), vec.y.to_f64()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let builder = SimulationBuilder::<f32>::default()
+            .with_boundary_modes(BoundaryMode::Periodic, BoundaryMode::Clamp)
+            .with_obstacle(Obstacle::Circle { center: Vec2 { x: 50., y: 50. }, radius: 10. })
+            .with_emitter(Emitter::new(Vec2 { x: 5., y: 5. }, Vec2 { x: 10., y: 0. }, 30., 4.).with_material(1))
+            .with_particles(vec![Particle::new(Vec2 { x: 1., y: 2. }, Vec2 { x: 3., y: 4. }).with_material(2)]);
+
+        let scene = Scene::from_json(&builder.to_scene().to_json().unwrap()).unwrap();
+        let loaded = SimulationBuilder::<f32>::from_scene(&scene).unwrap();
+
+        assert_eq!(loaded.boundary_x, BoundaryMode::Periodic);
+        assert_eq!(loaded.obstacles, builder.obstacles);
+        assert_eq!(loaded.emitters, builder.emitters);
+        assert_eq!(loaded.particles.len(), 1);
+        assert_eq!(loaded.particles[0].pos, Vec2 { x: 1., y: 2. });
+        assert_eq!(loaded.particles[0].material, 2);
+    }
+
+    #[test]
+    fn blocks_and_versions() {
+        let mut scene = SimulationBuilder::<f64>::default().to_scene();
+        scene.blocks.push(SceneBlock {
+            shape: SceneShape::Rect { min: [0., 0.], max: [9., 9.] },
+            spacing: 3.,
+            velocity: [0., 0.],
+            material: 0,
+        });
+
+        assert_eq!(SimulationBuilder::<f64>::from_scene(&scene).unwrap().particles.len(), 16);
+
+        // a tiny spacing is an error instead of an endless loop
+        scene.blocks[0].spacing = 1e-12;
+        assert!(matches!(SimulationBuilder::<f64>::from_scene(&scene), Err(SceneError::TooManyParticles(_))));
+
+        scene.version = SCENE_VERSION + 1;
+        let json = scene.to_json().unwrap();
+        assert!(matches!(Scene::from_json(&json), Err(SceneError::UnsupportedVersion(_))));
+    }
+
+    #[test]
+    fn example_scene() {
+        let scene = Scene::from_json(include_str!("../scenes/dam_break.json")).unwrap();
+        let builder = SimulationBuilder::<f32>::from_scene(&scene).unwrap();
+
+        assert!(!builder.particles.is_empty());
+        assert_eq!(builder.obstacles.len(), 1);
+    }
+}
@@ -0,0 +1,275 @@
+use crate::*;
+
+/// The 3D counterpart of `Simulation`, with the same particles, cell grid,
+/// double density relaxation and boundary modes. It has no force fields,
+/// interactions, obstacles or emitters yet, and always uses the scalar kernel.
+#[derive(Debug)]
+pub struct Simulation3<T = f32> {
+    pub gravity: Vec3<T>,
+    pub boundaries: Aabb<T>,
+    pub boundary_x: BoundaryMode,
+    pub boundary_y: BoundaryMode,
+    pub boundary_z: BoundaryMode,
+    pub(crate) interaction_radius: T,
+    pub pressure_multiplier: T,
+    pub near_pressure_multiplier: T,
+    pub rest_density: T,
+    pub(crate) particles: Vec<Particle3<T>>,
+    /// One cell is a cube with sides of `interaction_radius * 2.`.
+    /// Cell `(0, 0, 0)` starts from `(0., 0., 0.)`.
+    pub(crate) cells: CellGrid<(i32, i32, i32)>,
+}
+
+impl<T: Float> Simulation3<T> {
+    /// Must be called after building the simulation with `SimulationBuilder3`.
+    pub fn init(&mut self) {
+        self.update_cells();
+
+        // set prev_pos to equal current pos
+        for particle in &mut self.particles {
+            particle.prev_pos = particle.pos;
+        }
+    }
+
+    pub fn interaction_radius(&self) -> T {
+        self.interaction_radius
+    }
+
+    pub fn set_interaction_radius(&mut self, new_interaction_radius: T) {
+        self.interaction_radius = new_interaction_radius;
+        self.update_cells();
+
+        // set all prev_pos to current pos
+        for particle in &mut self.particles {
+            particle.prev_pos = particle.pos;
+        }
+    }
+
+    // clear all cells, then
+    // loops through all particles and puts them in the correct cell.
+    fn update_cells(&mut self) {
+        self.cells.empty();
+
+        let cell_size = self.cell_size();
+        for i in 0..self.particles.len() {
+            let cell = self.particles[i].pos.cell_key(cell_size);
+            self.cells.add(&mut self.particles, i, cell);
+        }
+    }
+
+    pub fn with_particles(particles: Vec<Particle3<T>>) -> Simulation3<T> {
+        Simulation3 {
+            particles,
+            ..Simulation3::default()
+        }
+    }
+
+    pub fn particles(&self) -> &Vec<Particle3<T>> {
+        &self.particles
+    }
+
+    pub fn step(&mut self, dt: T) {
+        for_each_mut(&mut self.particles, |particle| {
+            let (min, max) = (self.boundaries.min, self.boundaries.max);
+            self.boundary_x.apply(&mut particle.pos.x, &mut particle.vel.x, min.x, max.x);
+            self.boundary_y.apply(&mut particle.pos.y, &mut particle.vel.y, min.y, max.y);
+            self.boundary_z.apply(&mut particle.pos.z, &mut particle.vel.z, min.z, max.z);
+
+            particle.vel += self.gravity * dt;
+        });
+
+        if self.remove_escaped_particles() {
+            // indices have shifted, so the cells are rebuilt from scratch
+            self.update_cells();
+        } else {
+            let cell_size = self.cell_size();
+            self.cells.update(&mut self.particles, cell_size);
+        }
+
+        // update pos & prev_pos
+        for_each_mut(&mut self.particles, |particle| {
+            particle.prev_pos = particle.pos;
+            particle.pos += particle.vel * dt;
+        });
+
+        self.density_relaxation(dt);
+
+        for particle in &mut self.particles {
+            particle.vel = (particle.pos - particle.prev_pos) / dt;
+            particle.age += dt;
+        }
+    }
+
+    // removes the particles that left through an open boundary.
+    // returns whether any particle was removed.
+    fn remove_escaped_particles(&mut self) -> bool {
+        let (min, max) = (self.boundaries.min, self.boundaries.max);
+        let (boundary_x, boundary_y, boundary_z) = (self.boundary_x, self.boundary_y, self.boundary_z);
+        let len = self.particles.len();
+
+        self.particles.retain(|particle| {
+            !(boundary_x.escaped(particle.pos.x, min.x, max.x)
+                || boundary_y.escaped(particle.pos.y, min.y, max.y)
+                || boundary_z.escaped(particle.pos.z, min.z, max.z))
+        });
+
+        self.particles.len() != len
+    }
+
+    fn density_relaxation(&mut self, dt: T) {
+        let relaxation = Relaxation {
+            interaction_radius: self.interaction_radius,
+            pressure_multiplier: self.pressure_multiplier,
+            near_pressure_multiplier: self.near_pressure_multiplier,
+            rest_density: self.rest_density,
+        };
+        let (boundaries, boundary_x, boundary_y, boundary_z) = (self.boundaries, self.boundary_x, self.boundary_y, self.boundary_z);
+
+        for (cell, indices) in self.cells.iter() {
+            let neighbors = self.neighbors_from_cell(*cell);
+
+            relaxation.relax_cell(&mut self.particles, indices, &neighbors, dt, |from, to| {
+                let diff = to - from;
+
+                Vec3 {
+                    x: boundary_x.wrap_diff(diff.x, boundaries.width()),
+                    y: boundary_y.wrap_diff(diff.y, boundaries.height()),
+                    z: boundary_z.wrap_diff(diff.z, boundaries.depth()),
+                }
+            });
+        }
+    }
+
+    fn neighbors_from_cell(&self, cell: (i32, i32, i32)) -> Vec<usize> {
+        let (min, max) = (self.boundaries.min, self.boundaries.max);
+        let cell_size = self.cell_size();
+        let xs = self.boundary_x.neighbor_cell_keys(cell.0, min.x, max.x, cell_size);
+        let ys = self.boundary_y.neighbor_cell_keys(cell.1, min.y, max.y, cell_size);
+        let zs = self.boundary_z.neighbor_cell_keys(cell.2, min.z, max.z, cell_size);
+
+        let mut keys = vec![];
+        for &z in &zs {
+            for &y in &ys {
+                for &x in &xs {
+                    keys.push((x, y, z));
+                }
+            }
+        }
+
+        self.cells.neighbors(&keys)
+    }
+
+    fn cell_size(&self) -> T {
+        self.interaction_radius * T::from_f32(2.)
+    }
+}
+
+impl<T: Float> Default for Simulation3<T> {
+    fn default() -> Simulation3<T> {
+        SimulationBuilder3::default().build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn particles_settle_inside_boundaries() {
+        let mut particles = vec![];
+        for i in 0..125 {
+            particles.push(Particle3::new(
+                Vec3 {
+                    x: (i % 5) as f32 * 7. + 20.,
+                    y: (i / 5 % 5) as f32 * 7. + 20.,
+                    z: (i / 25) as f32 * 7. + 20.,
+                },
+                Vec3 { x: 0., y: 0., z: 0. },
+            ));
+        }
+
+        let mut simulation = SimulationBuilder3::default()
+            .with_boundaries(Aabb {
+                min: Vec3 { x: 0., y: 0., z: 0. },
+                max: Vec3 { x: 100., y: 100., z: 100. },
+            })
+            .with_particles(particles)
+            .build();
+        simulation.init();
+
+        for _ in 0..120 {
+            simulation.step(1. / 60.);
+        }
+
+        // particles can overshoot by one step's worth of movement
+        for particle in simulation.particles() {
+            assert!(particle.pos.x > -10. && particle.pos.x < 110.);
+            assert!(particle.pos.y > -10. && particle.pos.y < 110.);
+            assert!(particle.pos.z > -10. && particle.pos.z < 110.);
+        }
+    }
+
+    #[test]
+    fn boundary_modes() {
+        let particles = vec![
+            Particle3::new(Vec3 { x: 99., y: 50., z: 50. }, Vec3 { x: 120., y: 0., z: 0. }),
+            Particle3::new(Vec3 { x: 20., y: 50., z: 99. }, Vec3 { x: 0., y: 0., z: 120. }),
+        ];
+
+        let mut simulation = SimulationBuilder3::default()
+            .with_boundaries(Aabb {
+                min: Vec3 { x: 0., y: 0., z: 0. },
+                max: Vec3 { x: 100., y: 100., z: 100. },
+            })
+            .with_boundary_modes(BoundaryMode::Periodic, BoundaryMode::Clamp, BoundaryMode::Open)
+            .with_gravity(Vec3 { x: 0., y: 0., z: 0. })
+            .with_particles(particles)
+            .build();
+        simulation.init();
+
+        for _ in 0..3 {
+            simulation.step(1. / 60.);
+        }
+
+        // the first particle wrapped around to the left, the second one left
+        assert_eq!(simulation.particles().len(), 1);
+        assert!(simulation.particles()[0].pos.x < 10.);
+    }
+
+    #[test]
+    fn flat_layer_matches_2d() {
+        // a single layer of particles never moves along z, so it should
+        // settle exactly like the same particles in 2D
+        let positions: Vec<(f32, f32)> = (0..100).map(|i| ((i % 10) as f32 * 6. + 20., (i / 10) as f32 * 6. + 20.)).collect();
+
+        let mut simulation = SimulationBuilder::default()
+            .with_boundaries(Rect { min: Vec2 { x: 0., y: 0. }, max: Vec2 { x: 100., y: 100. } })
+            .with_particles(positions.iter().map(|&(x, y)| Particle::new(Vec2 { x, y }, Vec2 { x: 0., y: 0. })).collect())
+            .build();
+        simulation.init();
+
+        let mut simulation3 = SimulationBuilder3::default()
+            .with_boundaries(Aabb {
+                min: Vec3 { x: 0., y: 0., z: 0. },
+                max: Vec3 { x: 100., y: 100., z: 100. },
+            })
+            .with_particles(positions.iter().map(|&(x, y)| Particle3::new(Vec3 { x, y, z: 50. }, Vec3 { x: 0., y: 0., z: 0. })).collect())
+            .build();
+        simulation3.init();
+
+        for _ in 0..120 {
+            simulation.step(1. / 60.);
+            simulation3.step(1. / 60.);
+        }
+
+        let mean_density = |densities: Vec<f32>| densities.iter().sum::<f32>() / densities.len() as f32;
+        let density = mean_density(simulation.particles().iter().map(|particle| particle.density).collect());
+        let density3 = mean_density(simulation3.particles().iter().map(|particle| particle.density).collect());
+
+        assert!(density > 0.);
+        assert!((density - density3).abs() < 1e-3);
+        for (particle, particle3) in simulation.particles().iter().zip(simulation3.particles()) {
+            assert!((particle.pos.x - particle3.pos.x).abs() < 1e-3 && particle3.pos.z == 50.);
+        }
+    }
+}
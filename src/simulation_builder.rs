@@ -1,92 +1,152 @@
 use crate::*;
 
+use std::sync::Arc;
+
 #[derive(Debug, Clone)]
-pub struct SimulationBuilder {
-    pub gravity: f32,
-    pub boundaries: Rect,
-    pub interaction_radius: f32,
-    pub pressure_multiplier: f32,
-    pub near_pressure_multiplier: f32,
-    pub rest_density: f32,
-    pub particles: Vec<Particle>,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SimulationBuilder<T = f32> {
+    pub gravity: Vec2<T>,
+    /// Not serialized, since force fields are trait objects.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub force_fields: Vec<Arc<dyn ForceField<T>>>,
+    pub obstacles: Vec<Obstacle<T>>,
+    pub emitters: Vec<Emitter<T>>,
+    pub boundaries: Rect<T>,
+    pub boundary_x: BoundaryMode,
+    pub boundary_y: BoundaryMode,
+    pub interaction_radius: T,
+    pub pressure_multiplier: T,
+    pub near_pressure_multiplier: T,
+    pub rest_density: T,
+    pub kernel: RelaxationKernel,
+    pub particles: Vec<Particle<T>>,
 }
 
-impl SimulationBuilder {
-    pub fn with_gravity(&self, gravity: f32) -> SimulationBuilder {
+impl<T: Float> SimulationBuilder<T> {
+    pub fn with_gravity(&self, gravity: Vec2<T>) -> SimulationBuilder<T> {
         SimulationBuilder {
             gravity,
             ..self.clone()
         }
     }
 
-    pub fn with_boundaries(&self, boundaries: Rect) -> SimulationBuilder {
+    /// Adds a force field on top of the ones already added.
+    pub fn with_force_field(&self, force_field: impl ForceField<T> + 'static) -> SimulationBuilder<T> {
+        let mut builder = self.clone();
+        builder.force_fields.push(Arc::new(force_field));
+        builder
+    }
+
+    pub fn with_obstacle(&self, obstacle: Obstacle<T>) -> SimulationBuilder<T> {
+        let mut builder = self.clone();
+        builder.obstacles.push(obstacle);
+        builder
+    }
+
+    pub fn with_emitter(&self, emitter: Emitter<T>) -> SimulationBuilder<T> {
+        let mut builder = self.clone();
+        builder.emitters.push(emitter);
+        builder
+    }
+
+    pub fn with_boundaries(&self, boundaries: Rect<T>) -> SimulationBuilder<T> {
         SimulationBuilder {
             boundaries,
             ..self.clone()
         }
     }
 
-    pub fn with_interaction_radius(&self, interaction_radius: f32) -> SimulationBuilder {
+    pub fn with_boundary_modes(&self, boundary_x: BoundaryMode, boundary_y: BoundaryMode) -> SimulationBuilder<T> {
+        SimulationBuilder {
+            boundary_x,
+            boundary_y,
+            ..self.clone()
+        }
+    }
+
+    pub fn with_interaction_radius(&self, interaction_radius: T) -> SimulationBuilder<T> {
         SimulationBuilder {
             interaction_radius,
             ..self.clone()
         }
     }
 
-    pub fn with_pressure_multiplier(&self, pressure_multiplier: f32) -> SimulationBuilder {
+    pub fn with_pressure_multiplier(&self, pressure_multiplier: T) -> SimulationBuilder<T> {
         SimulationBuilder {
             pressure_multiplier,
             ..self.clone()
         }
     }
 
-    pub fn with_near_pressure_multiplier(&self, near_pressure_multiplier: f32) -> SimulationBuilder {
+    pub fn with_near_pressure_multiplier(&self, near_pressure_multiplier: T) -> SimulationBuilder<T> {
         SimulationBuilder {
             near_pressure_multiplier,
             ..self.clone()
         }
     }
 
-    pub fn rest_density(&self, rest_density: f32) -> SimulationBuilder {
+    pub fn rest_density(&self, rest_density: T) -> SimulationBuilder<T> {
         SimulationBuilder {
             rest_density,
             ..self.clone()
         }
     }
 
-    pub fn with_particles(&self, particles: Vec<Particle>) -> SimulationBuilder {
+    pub fn with_kernel(&self, kernel: RelaxationKernel) -> SimulationBuilder<T> {
+        SimulationBuilder {
+            kernel,
+            ..self.clone()
+        }
+    }
+
+    pub fn with_particles(&self, particles: Vec<Particle<T>>) -> SimulationBuilder<T> {
         SimulationBuilder {
             particles,
             ..self.clone()
         }
     }
 
-    pub fn build(&self) -> Simulation {
+    pub fn build(&self) -> Simulation<T> {
         Simulation {
             gravity: self.gravity,
+            force_fields: self.force_fields.clone(),
+            obstacles: self.obstacles.clone(),
+            emitters: self.emitters.clone(),
             boundaries: self.boundaries,
+            boundary_x: self.boundary_x,
+            boundary_y: self.boundary_y,
             interaction_radius: self.interaction_radius,
             pressure_multiplier: self.pressure_multiplier,
             near_pressure_multiplier: self.near_pressure_multiplier,
             rest_density: self.rest_density,
+            kernel: self.kernel,
+            interaction: None,
+            grabbed: vec![],
+            time: T::ZERO,
             particles: self.particles.clone(),
-            cells: HashMap::new(),
+            cells: CellGrid::default(),
         }
     }
 }
 
-impl Default for SimulationBuilder {
-    fn default() -> SimulationBuilder {
+impl<T: Float> Default for SimulationBuilder<T> {
+    fn default() -> SimulationBuilder<T> {
         SimulationBuilder {
-            gravity: 196.,
+            gravity: Vec2 { x: T::ZERO, y: T::from_f32(196.) },
+            force_fields: vec![],
+            obstacles: vec![],
+            emitters: vec![],
             boundaries: Rect {
-                min: Vec2 { x: 0., y: 0. },
-                max: Vec2 { x: 0., y: 0. },
+                min: Vec2 { x: T::ZERO, y: T::ZERO },
+                max: Vec2 { x: T::ZERO, y: T::ZERO },
             },
-            interaction_radius: 40.,
-            pressure_multiplier: 45.,
-            near_pressure_multiplier: 45.,
-            rest_density: 9.,
+            boundary_x: BoundaryMode::default(),
+            boundary_y: BoundaryMode::default(),
+            interaction_radius: T::from_f32(40.),
+            pressure_multiplier: T::from_f32(45.),
+            near_pressure_multiplier: T::from_f32(45.),
+            rest_density: T::from_f32(9.),
+            kernel: RelaxationKernel::default(),
             particles: vec![],
         }
     }
@@ -0,0 +1,112 @@
+use crate::*;
+
+/// The 3D counterpart of `SimulationBuilder`.
+#[derive(Debug, Clone)]
+pub struct SimulationBuilder3<T = f32> {
+    pub gravity: Vec3<T>,
+    pub boundaries: Aabb<T>,
+    pub boundary_x: BoundaryMode,
+    pub boundary_y: BoundaryMode,
+    pub boundary_z: BoundaryMode,
+    pub interaction_radius: T,
+    pub pressure_multiplier: T,
+    pub near_pressure_multiplier: T,
+    pub rest_density: T,
+    pub particles: Vec<Particle3<T>>,
+}
+
+impl<T: Float> SimulationBuilder3<T> {
+    pub fn with_gravity(&self, gravity: Vec3<T>) -> SimulationBuilder3<T> {
+        SimulationBuilder3 {
+            gravity,
+            ..self.clone()
+        }
+    }
+
+    pub fn with_boundaries(&self, boundaries: Aabb<T>) -> SimulationBuilder3<T> {
+        SimulationBuilder3 {
+            boundaries,
+            ..self.clone()
+        }
+    }
+
+    pub fn with_boundary_modes(&self, boundary_x: BoundaryMode, boundary_y: BoundaryMode, boundary_z: BoundaryMode) -> SimulationBuilder3<T> {
+        SimulationBuilder3 {
+            boundary_x,
+            boundary_y,
+            boundary_z,
+            ..self.clone()
+        }
+    }
+
+    pub fn with_interaction_radius(&self, interaction_radius: T) -> SimulationBuilder3<T> {
+        SimulationBuilder3 {
+            interaction_radius,
+            ..self.clone()
+        }
+    }
+
+    pub fn with_pressure_multiplier(&self, pressure_multiplier: T) -> SimulationBuilder3<T> {
+        SimulationBuilder3 {
+            pressure_multiplier,
+            ..self.clone()
+        }
+    }
+
+    pub fn with_near_pressure_multiplier(&self, near_pressure_multiplier: T) -> SimulationBuilder3<T> {
+        SimulationBuilder3 {
+            near_pressure_multiplier,
+            ..self.clone()
+        }
+    }
+
+    pub fn rest_density(&self, rest_density: T) -> SimulationBuilder3<T> {
+        SimulationBuilder3 {
+            rest_density,
+            ..self.clone()
+        }
+    }
+
+    pub fn with_particles(&self, particles: Vec<Particle3<T>>) -> SimulationBuilder3<T> {
+        SimulationBuilder3 {
+            particles,
+            ..self.clone()
+        }
+    }
+
+    pub fn build(&self) -> Simulation3<T> {
+        Simulation3 {
+            gravity: self.gravity,
+            boundaries: self.boundaries,
+            boundary_x: self.boundary_x,
+            boundary_y: self.boundary_y,
+            boundary_z: self.boundary_z,
+            interaction_radius: self.interaction_radius,
+            pressure_multiplier: self.pressure_multiplier,
+            near_pressure_multiplier: self.near_pressure_multiplier,
+            rest_density: self.rest_density,
+            particles: self.particles.clone(),
+            cells: CellGrid::default(),
+        }
+    }
+}
+
+impl<T: Float> Default for SimulationBuilder3<T> {
+    fn default() -> SimulationBuilder3<T> {
+        SimulationBuilder3 {
+            gravity: Vec3 { x: T::ZERO, y: T::from_f32(196.), z: T::ZERO },
+            boundaries: Aabb {
+                min: Vec3 { x: T::ZERO, y: T::ZERO, z: T::ZERO },
+                max: Vec3 { x: T::ZERO, y: T::ZERO, z: T::ZERO },
+            },
+            boundary_x: BoundaryMode::default(),
+            boundary_y: BoundaryMode::default(),
+            boundary_z: BoundaryMode::default(),
+            interaction_radius: T::from_f32(40.),
+            pressure_multiplier: T::from_f32(45.),
+            near_pressure_multiplier: T::from_f32(45.),
+            rest_density: T::from_f32(9.),
+            particles: vec![],
+        }
+    }
+}
@@ -0,0 +1,377 @@
+use crate::*;
+
+/// Number of lanes processed at once by the chunked kernels.
+pub const LANES: usize = 16;
+
+/// Structure-of-arrays particle storage.
+/// Each field of `Particle` is kept in its own array, so the same field of
+/// consecutive particles is contiguous in memory.
+#[derive(Debug, Clone, Default)]
+pub struct ParticleSoa<T = f32> {
+    pub pos_x: Vec<T>,
+    pub pos_y: Vec<T>,
+    pub vel_x: Vec<T>,
+    pub vel_y: Vec<T>,
+    pub prev_pos_x: Vec<T>,
+    pub prev_pos_y: Vec<T>,
+    pub cell_index: Vec<usize>,
+    pub material: Vec<u32>,
+    pub density: Vec<T>,
+    pub near_density: Vec<T>,
+    pub pressure: Vec<T>,
+    pub near_pressure: Vec<T>,
+    pub age: Vec<T>,
+}
+
+impl<T: Float> ParticleSoa<T> {
+    pub fn new() -> ParticleSoa<T> {
+        ParticleSoa::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pos_x.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos_x.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.pos_x.clear();
+        self.pos_y.clear();
+        self.vel_x.clear();
+        self.vel_y.clear();
+        self.prev_pos_x.clear();
+        self.prev_pos_y.clear();
+        self.cell_index.clear();
+        self.material.clear();
+        self.density.clear();
+        self.near_density.clear();
+        self.pressure.clear();
+        self.near_pressure.clear();
+        self.age.clear();
+    }
+
+    pub fn push(&mut self, particle: Particle<T>) {
+        self.pos_x.push(particle.pos.x);
+        self.pos_y.push(particle.pos.y);
+        self.vel_x.push(particle.vel.x);
+        self.vel_y.push(particle.vel.y);
+        self.prev_pos_x.push(particle.prev_pos.x);
+        self.prev_pos_y.push(particle.prev_pos.y);
+        self.cell_index.push(particle.cell_index);
+        self.material.push(particle.material);
+        self.density.push(particle.density);
+        self.near_density.push(particle.near_density);
+        self.pressure.push(particle.pressure);
+        self.near_pressure.push(particle.near_pressure);
+        self.age.push(particle.age);
+    }
+
+    pub fn get(&self, index: usize) -> Particle<T> {
+        Particle {
+            pos: Vec2 { x: self.pos_x[index], y: self.pos_y[index] },
+            vel: Vec2 { x: self.vel_x[index], y: self.vel_y[index] },
+            prev_pos: Vec2 { x: self.prev_pos_x[index], y: self.prev_pos_y[index] },
+            cell_index: self.cell_index[index],
+            material: self.material[index],
+            density: self.density[index],
+            near_density: self.near_density[index],
+            pressure: self.pressure[index],
+            near_pressure: self.near_pressure[index],
+            age: self.age[index],
+        }
+    }
+
+    pub fn set(&mut self, index: usize, particle: Particle<T>) {
+        self.pos_x[index] = particle.pos.x;
+        self.pos_y[index] = particle.pos.y;
+        self.vel_x[index] = particle.vel.x;
+        self.vel_y[index] = particle.vel.y;
+        self.prev_pos_x[index] = particle.prev_pos.x;
+        self.prev_pos_y[index] = particle.prev_pos.y;
+        self.cell_index[index] = particle.cell_index;
+        self.material[index] = particle.material;
+        self.density[index] = particle.density;
+        self.near_density[index] = particle.near_density;
+        self.pressure[index] = particle.pressure;
+        self.near_pressure[index] = particle.near_pressure;
+        self.age[index] = particle.age;
+    }
+
+    /// Clears the storage, then copies the particles at `indices` into it.
+    pub fn gather(&mut self, particles: &[Particle<T>], indices: &[usize]) {
+        self.clear();
+
+        for &i in indices {
+            self.push(particles[i]);
+        }
+    }
+
+    /// Copies the storage back into `particles` at `indices`.
+    /// The inverse of `gather`.
+    pub fn scatter(&self, particles: &mut [Particle<T>], indices: &[usize]) {
+        for (n, &i) in indices.iter().enumerate() {
+            particles[i] = self.get(n);
+        }
+    }
+
+    pub fn to_particles(&self) -> Vec<Particle<T>> {
+        (0..self.len()).map(|i| self.get(i)).collect()
+    }
+}
+
+impl<T: Float> From<&[Particle<T>]> for ParticleSoa<T> {
+    fn from(particles: &[Particle<T>]) -> ParticleSoa<T> {
+        let mut soa = ParticleSoa::new();
+
+        for &particle in particles {
+            soa.push(particle);
+        }
+
+        soa
+    }
+}
+
+/// Computes the density and near density at `pos` from the neighbors in
+/// `xs`/`ys`. The neighbor at `skip` (usually the particle itself) is ignored.
+pub fn density_chunked<T: Float>(
+    pos: Vec2<T>,
+    xs: &[T],
+    ys: &[T],
+    skip: Option<usize>,
+    interaction_radius: T,
+) -> (T, T) {
+    let one = T::ONE;
+    let inv_radius = one / interaction_radius;
+
+    let mut density = [T::ZERO; LANES];
+    let mut near_density = [T::ZERO; LANES];
+
+    let mut x_chunks = xs.chunks_exact(LANES);
+    let mut y_chunks = ys.chunks_exact(LANES);
+
+    for (x_chunk, y_chunk) in (&mut x_chunks).zip(&mut y_chunks) {
+        for lane in 0..LANES {
+            let dx = pos.x - x_chunk[lane];
+            let dy = pos.y - y_chunk[lane];
+            let q = (dx * dx + dy * dy).sqrt() * inv_radius;
+            let one_minus_q = if q < one { one - q } else { T::ZERO };
+
+            density[lane] += one_minus_q * one_minus_q;
+            near_density[lane] += one_minus_q * one_minus_q * one_minus_q;
+        }
+    }
+
+    let mut density: T = density.iter().copied().sum();
+    let mut near_density: T = near_density.iter().copied().sum();
+
+    for (&x, &y) in x_chunks.remainder().iter().zip(y_chunks.remainder()) {
+        let dx = pos.x - x;
+        let dy = pos.y - y;
+        let q = (dx * dx + dy * dy).sqrt() * inv_radius;
+
+        if q < one {
+            density += (one - q) * (one - q);
+            near_density += (one - q) * (one - q) * (one - q);
+        }
+    }
+
+    // the chunked loop has no branches, so remove the skipped neighbor afterwards
+    if let Some(skip) = skip {
+        let dx = pos.x - xs[skip];
+        let dy = pos.y - ys[skip];
+        let q = (dx * dx + dy * dy).sqrt() * inv_radius;
+
+        if q < one {
+            density -= (one - q) * (one - q);
+            near_density -= (one - q) * (one - q) * (one - q);
+        }
+    }
+
+    (density, near_density)
+}
+
+/// Computes the displacement applied to each neighbor in `xs`/`ys` by the
+/// particle at `pos`, writing it into `out_x`/`out_y`.
+/// The neighbor at `skip` is given a displacement of zero.
+#[allow(clippy::too_many_arguments)]
+pub fn displacement_chunked<T: Float>(
+    pos: Vec2<T>,
+    xs: &[T],
+    ys: &[T],
+    skip: Option<usize>,
+    interaction_radius: T,
+    pressure: T,
+    near_pressure: T,
+    dt: T,
+    out_x: &mut Vec<T>,
+    out_y: &mut Vec<T>,
+) {
+    let one = T::ONE;
+    let inv_radius = one / interaction_radius;
+    let dt2 = dt * dt;
+
+    out_x.clear();
+    out_y.clear();
+    out_x.resize(xs.len(), T::ZERO);
+    out_y.resize(ys.len(), T::ZERO);
+
+    let displacement = |dx: T, dy: T| {
+        let dist = (dx * dx + dy * dy).sqrt();
+        let q = dist * inv_radius;
+        let one_minus_q = (one - q).max(T::ZERO);
+        let inv_dist = if dist > T::ZERO { one / dist } else { T::ZERO };
+        let magnitude = (pressure * one_minus_q + near_pressure * one_minus_q * one_minus_q) * dt2 * inv_dist;
+
+        (dx * magnitude, dy * magnitude)
+    };
+
+    let mut x_chunks = xs.chunks_exact(LANES);
+    let mut y_chunks = ys.chunks_exact(LANES);
+    let mut out_x_chunks = out_x.chunks_exact_mut(LANES);
+    let mut out_y_chunks = out_y.chunks_exact_mut(LANES);
+
+    for (((x_chunk, y_chunk), out_x_chunk), out_y_chunk) in (&mut x_chunks)
+        .zip(&mut y_chunks)
+        .zip(&mut out_x_chunks)
+        .zip(&mut out_y_chunks)
+    {
+        for lane in 0..LANES {
+            (out_x_chunk[lane], out_y_chunk[lane]) = displacement(x_chunk[lane] - pos.x, y_chunk[lane] - pos.y);
+        }
+    }
+
+    for (((&x, &y), out_x), out_y) in x_chunks.remainder().iter()
+        .zip(y_chunks.remainder())
+        .zip(out_x_chunks.into_remainder())
+        .zip(out_y_chunks.into_remainder())
+    {
+        (*out_x, *out_y) = displacement(x - pos.x, y - pos.y);
+    }
+
+    if let Some(skip) = skip {
+        out_x[skip] = T::ZERO;
+        out_y[skip] = T::ZERO;
+    }
+}
+
+/// Moves every neighbor in `xs`/`ys` by half of its displacement, and
+/// returns the opposite of the other half, which should be applied to the
+/// particle that caused the displacements.
+pub fn apply_displacement_chunked<T: Float>(
+    xs: &mut [T],
+    ys: &mut [T],
+    displacement_x: &[T],
+    displacement_y: &[T],
+) -> Vec2<T> {
+    let half = T::from_f32(0.5);
+
+    let mut dpos_x = [T::ZERO; LANES];
+    let mut dpos_y = [T::ZERO; LANES];
+
+    let mut x_chunks = xs.chunks_exact_mut(LANES);
+    let mut y_chunks = ys.chunks_exact_mut(LANES);
+    let mut dx_chunks = displacement_x.chunks_exact(LANES);
+    let mut dy_chunks = displacement_y.chunks_exact(LANES);
+
+    for (((x_chunk, y_chunk), dx_chunk), dy_chunk) in (&mut x_chunks)
+        .zip(&mut y_chunks)
+        .zip(&mut dx_chunks)
+        .zip(&mut dy_chunks)
+    {
+        for lane in 0..LANES {
+            x_chunk[lane] += dx_chunk[lane] * half;
+            y_chunk[lane] += dy_chunk[lane] * half;
+            dpos_x[lane] -= dx_chunk[lane] * half;
+            dpos_y[lane] -= dy_chunk[lane] * half;
+        }
+    }
+
+    let mut dpos = Vec2 {
+        x: dpos_x.iter().copied().sum(),
+        y: dpos_y.iter().copied().sum(),
+    };
+
+    for (((x, y), &dx), &dy) in x_chunks.into_remainder().iter_mut()
+        .zip(y_chunks.into_remainder())
+        .zip(dx_chunks.remainder())
+        .zip(dy_chunks.remainder())
+    {
+        *x += dx * half;
+        *y += dy * half;
+        dpos.x -= dx * half;
+        dpos.y -= dy * half;
+    }
+
+    dpos
+}
+
+/// Which implementation `Simulation::step` uses for density relaxation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RelaxationKernel {
+    /// One neighbor at a time, directly on `Particle`.
+    #[default]
+    Scalar,
+    /// Neighbors are gathered into a `ParticleSoa` and processed `LANES` at a time.
+    /// Falls back to `Scalar` while a periodic axis is at most 4 interaction
+    /// radii wide.
+    Chunked,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions() -> (Vec<f32>, Vec<f32>) {
+        (0..37)
+            .map(|i| ((i % 6) as f32 * 9. + 3., (i / 6) as f32 * 11. - 5.))
+            .unzip()
+    }
+
+    #[test]
+    fn density_chunked() {
+        let (xs, ys) = positions();
+        let pos = Vec2 { x: xs[14], y: ys[14] };
+
+        let mut density = 0.;
+        let mut near_density = 0.;
+        for j in 0..xs.len() {
+            if j == 14 { continue; }
+
+            let q = Vec2::dist(pos - Vec2 { x: xs[j], y: ys[j] }) / 40.;
+            if q < 1. {
+                density += (1. - q) * (1. - q);
+                near_density += (1. - q) * (1. - q) * (1. - q);
+            }
+        }
+
+        let (chunked_density, chunked_near_density) = super::density_chunked(pos, &xs, &ys, Some(14), 40.);
+        assert!((density - chunked_density).abs() < 1e-4);
+        assert!((near_density - chunked_near_density).abs() < 1e-4);
+    }
+
+    #[test]
+    fn displacement_chunked() {
+        let (xs, ys) = positions();
+        let pos = Vec2 { x: xs[14], y: ys[14] };
+        let dt = 1. / 60.;
+
+        let mut out_x = vec![];
+        let mut out_y = vec![];
+        super::displacement_chunked(pos, &xs, &ys, Some(14), 40., 3., 5., dt, &mut out_x, &mut out_y);
+
+        for j in 0..xs.len() {
+            let diff = Vec2 { x: xs[j], y: ys[j] } - pos;
+            let q = Vec2::dist(diff) / 40.;
+            let expected = if j == 14 || q >= 1. {
+                Vec2 { x: 0., y: 0. }
+            } else {
+                diff.normalize() * (3. * (1. - q) + 5. * (1. - q) * (1. - q)) * dt * dt
+            };
+
+            assert!(Vec2::dist(expected - Vec2 { x: out_x[j], y: out_y[j] }) < 1e-6);
+        }
+    }
+}
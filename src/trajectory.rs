@@ -0,0 +1,325 @@
+//! Gzip compressed recordings of particle positions, for reviewing
+//! expensive runs without simulating them again.
+//!
+//! A trajectory file is a header followed by frames, all in one gzip stream.
+//! Each frame stores the time, the particle positions and their materials.
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use crate::*;
+
+const TRAJECTORY_MAGIC: &[u8; 8] = b"FSIMTRAJ";
+
+/// The trajectory file version written by `TrajectoryRecorder`.
+pub const TRAJECTORY_VERSION: u32 = 1;
+
+// frame lengths are read from the file, so at most this many particles are
+// allocated up front. longer frames grow as their particles are read.
+const MAX_RESERVED_PARTICLES: usize = 1 << 16;
+
+/// How positions are stored in a trajectory file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrajectoryEncoding {
+    /// 32-bit floats.
+    #[default]
+    Full,
+    /// 16 bits per coordinate, relative to the recorded boundaries.
+    /// Positions outside the boundaries are clamped to them.
+    Quantized,
+}
+
+/// Streams every `interval`th step of a simulation into a trajectory file.
+#[derive(Debug)]
+pub struct TrajectoryRecorder<W: Write> {
+    encoder: GzEncoder<W>,
+    boundaries: Rect<f64>,
+    interval: usize,
+    encoding: TrajectoryEncoding,
+    // steps since recording started
+    steps: usize,
+}
+
+impl<W: Write> TrajectoryRecorder<W> {
+    /// `boundaries` are only used by `TrajectoryEncoding::Quantized`, and to
+    /// frame the replay.
+    pub fn new<T: Float>(writer: W, boundaries: Rect<T>, interval: usize, encoding: TrajectoryEncoding) -> io::Result<TrajectoryRecorder<W>> {
+        let interval = interval.max(1);
+        let boundaries = Rect {
+            min: Vec2 { x: boundaries.min.x.to_f64(), y: boundaries.min.y.to_f64() },
+            max: Vec2 { x: boundaries.max.x.to_f64(), y: boundaries.max.y.to_f64() },
+        };
+
+        let mut encoder = GzEncoder::new(writer, Compression::default());
+        encoder.write_all(TRAJECTORY_MAGIC)?;
+        encoder.write_all(&TRAJECTORY_VERSION.to_le_bytes())?;
+        encoder.write_all(&[match encoding {
+            TrajectoryEncoding::Full => 0,
+            TrajectoryEncoding::Quantized => 1,
+        }])?;
+        for value in [boundaries.min.x, boundaries.min.y, boundaries.max.x, boundaries.max.y] {
+            encoder.write_all(&value.to_le_bytes())?;
+        }
+        encoder.write_all(&(interval as u32).to_le_bytes())?;
+
+        Ok(TrajectoryRecorder { encoder, boundaries, interval, encoding, steps: 0 })
+    }
+
+    /// Called after every step. Only every `interval`th call writes a frame.
+    pub fn record<T: Float>(&mut self, simulation: &Simulation<T>) -> io::Result<()> {
+        let due = self.steps.is_multiple_of(self.interval);
+        self.steps += 1;
+
+        if due {
+            self.write_frame(simulation)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn write_frame<T: Float>(&mut self, simulation: &Simulation<T>) -> io::Result<()> {
+        let particles = simulation.particles();
+
+        self.encoder.write_all(&simulation.time().to_f64().to_le_bytes())?;
+        self.encoder.write_all(&(particles.len() as u32).to_le_bytes())?;
+
+        for particle in particles {
+            let (x, y) = (particle.pos.x.to_f64(), particle.pos.y.to_f64());
+
+            match self.encoding {
+                TrajectoryEncoding::Full => {
+                    self.encoder.write_all(&(x as f32).to_le_bytes())?;
+                    self.encoder.write_all(&(y as f32).to_le_bytes())?;
+                }
+                TrajectoryEncoding::Quantized => {
+                    let b = self.boundaries;
+                    self.encoder.write_all(&quantize(x, b.min.x, b.max.x).to_le_bytes())?;
+                    self.encoder.write_all(&quantize(y, b.min.y, b.max.y).to_le_bytes())?;
+                }
+            }
+        }
+
+        for particle in particles {
+            self.encoder.write_all(&particle.material.to_le_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    /// Finishes the gzip stream and returns the writer.
+    pub fn finish(self) -> io::Result<W> {
+        self.encoder.finish()
+    }
+}
+
+impl TrajectoryRecorder<BufWriter<File>> {
+    pub fn create<T: Float>(path: impl AsRef<Path>, boundaries: Rect<T>, interval: usize, encoding: TrajectoryEncoding) -> io::Result<TrajectoryRecorder<BufWriter<File>>> {
+        TrajectoryRecorder::new(BufWriter::new(File::create(path)?), boundaries, interval, encoding)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrajectoryFrame {
+    pub time: f64,
+    pub positions: Vec<Vec2>,
+    pub materials: Vec<u32>,
+}
+
+/// A trajectory file read back into memory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trajectory {
+    pub boundaries: Rect<f64>,
+    /// Steps between frames.
+    pub interval: usize,
+    pub frames: Vec<TrajectoryFrame>,
+}
+
+impl Trajectory {
+    pub fn read_from(reader: impl Read) -> io::Result<Trajectory> {
+        let mut reader = GzDecoder::new(reader);
+
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+
+        if &magic != TRAJECTORY_MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a trajectory file"));
+        }
+
+        let version = u32::from_le_bytes(read_bytes(&mut reader)?);
+
+        if version > TRAJECTORY_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported trajectory version {version}, expected at most {TRAJECTORY_VERSION}"),
+            ));
+        }
+
+        let encoding = match read_bytes::<1>(&mut reader)?[0] {
+            0 => TrajectoryEncoding::Full,
+            1 => TrajectoryEncoding::Quantized,
+            tag => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("invalid encoding {tag}"))),
+        };
+
+        let mut bounds = [0.; 4];
+        for value in &mut bounds {
+            *value = f64::from_le_bytes(read_bytes(&mut reader)?);
+        }
+        let boundaries = Rect {
+            min: Vec2 { x: bounds[0], y: bounds[1] },
+            max: Vec2 { x: bounds[2], y: bounds[3] },
+        };
+
+        let interval = u32::from_le_bytes(read_bytes(&mut reader)?) as usize;
+
+        let mut frames = vec![];
+        // the stream ends after the last complete frame
+        while let Some(time) = read_bytes_or_end(&mut reader)? {
+            let time = f64::from_le_bytes(time);
+            let len = u32::from_le_bytes(read_bytes(&mut reader)?) as usize;
+
+            // a frame with fewer particles than its length is corrupt
+            let truncated = |err: io::Error| match err.kind() {
+                io::ErrorKind::UnexpectedEof => {
+                    io::Error::new(io::ErrorKind::InvalidData, format!("frame at {time} is shorter than its {len} particles"))
+                }
+                _ => err,
+            };
+
+            let mut positions = Vec::with_capacity(len.min(MAX_RESERVED_PARTICLES));
+            for _ in 0..len {
+                positions.push(match encoding {
+                    TrajectoryEncoding::Full => Vec2 {
+                        x: f32::from_le_bytes(read_bytes(&mut reader).map_err(truncated)?),
+                        y: f32::from_le_bytes(read_bytes(&mut reader).map_err(truncated)?),
+                    },
+                    TrajectoryEncoding::Quantized => Vec2 {
+                        x: dequantize(u16::from_le_bytes(read_bytes(&mut reader).map_err(truncated)?), boundaries.min.x, boundaries.max.x),
+                        y: dequantize(u16::from_le_bytes(read_bytes(&mut reader).map_err(truncated)?), boundaries.min.y, boundaries.max.y),
+                    },
+                });
+            }
+
+            let mut materials = Vec::with_capacity(positions.len());
+            for _ in 0..len {
+                materials.push(u32::from_le_bytes(read_bytes(&mut reader).map_err(truncated)?));
+            }
+
+            frames.push(TrajectoryFrame { time, positions, materials });
+        }
+
+        Ok(Trajectory { boundaries, interval, frames })
+    }
+
+    pub fn read_file(path: impl AsRef<Path>) -> io::Result<Trajectory> {
+        Trajectory::read_from(BufReader::new(File::open(path)?))
+    }
+}
+
+fn quantize(value: f64, min: f64, max: f64) -> u16 {
+    ((value - min) / (max - min) * u16::MAX as f64).round().clamp(0., u16::MAX as f64) as u16
+}
+
+fn dequantize(value: u16, min: f64, max: f64) -> f32 {
+    (min + value as f64 / u16::MAX as f64 * (max - min)) as f32
+}
+
+fn read_bytes<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+// like `read_bytes`, but returns `None` if the reader is already at its end.
+fn read_bytes_or_end<const N: usize>(reader: &mut impl Read) -> io::Result<Option<[u8; N]>> {
+    let mut bytes = [0; N];
+    let mut read = 0;
+
+    while read < N {
+        match reader.read(&mut bytes[read..])? {
+            0 if read == 0 => return Ok(None),
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            n => read += n,
+        }
+    }
+
+    Ok(Some(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(encoding: TrajectoryEncoding) -> (Simulation, Trajectory) {
+        let particles = (0..20)
+            .map(|i| Particle::new(Vec2 { x: 20. + (i % 5) as f32 * 10., y: 20. + (i / 5) as f32 * 10. }, Vec2 { x: 0., y: 0. }).with_material(i % 3))
+            .collect();
+        let boundaries = Rect { min: Vec2 { x: 0., y: 0. }, max: Vec2 { x: 200., y: 200. } };
+        let mut simulation = SimulationBuilder::default()
+            .with_boundaries(boundaries)
+            .with_particles(particles)
+            .build();
+        simulation.init();
+
+        let mut recorder = TrajectoryRecorder::new(vec![], boundaries, 5, encoding).unwrap();
+        recorder.record(&simulation).unwrap();
+
+        for _ in 0..10 {
+            simulation.step(1. / 60.);
+            recorder.record(&simulation).unwrap();
+        }
+
+        let bytes = recorder.finish().unwrap();
+        (simulation, Trajectory::read_from(bytes.as_slice()).unwrap())
+    }
+
+    #[test]
+    fn full() {
+        let (simulation, trajectory) = record(TrajectoryEncoding::Full);
+
+        // steps 0, 5 and 10
+        assert_eq!(trajectory.frames.len(), 3);
+        assert_eq!(trajectory.interval, 5);
+
+        let last = &trajectory.frames[2];
+        assert_eq!(last.time, simulation.time() as f64);
+        assert_eq!(last.materials[4], 1);
+        for (pos, particle) in last.positions.iter().zip(simulation.particles()) {
+            assert_eq!(*pos, particle.pos);
+        }
+
+        // the particles moved between the first and the last frame
+        assert_ne!(trajectory.frames[0].positions, last.positions);
+    }
+
+    #[test]
+    fn corrupt_length() {
+        let boundaries = Rect { min: Vec2 { x: 0., y: 0. }, max: Vec2 { x: 200., y: 200. } };
+        let mut recorder = TrajectoryRecorder::new(vec![], boundaries, 1, TrajectoryEncoding::Full).unwrap();
+
+        // a frame claiming u32::MAX particles, followed by one
+        recorder.encoder.write_all(&0f64.to_le_bytes()).unwrap();
+        recorder.encoder.write_all(&u32::MAX.to_le_bytes()).unwrap();
+        recorder.encoder.write_all(&[0; 8]).unwrap();
+
+        let bytes = recorder.finish().unwrap();
+        let err = Trajectory::read_from(bytes.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn quantized() {
+        let (simulation, trajectory) = record(TrajectoryEncoding::Quantized);
+
+        for (pos, particle) in trajectory.frames[2].positions.iter().zip(simulation.particles()) {
+            assert!(Vec2::dist(*pos - particle.pos) <= 200. / u16::MAX as f32);
+        }
+
+        let mut moved = trajectory.frames[0].positions.iter().zip(&trajectory.frames[2].positions);
+        assert!(moved.any(|(first, last)| Vec2::dist(*last - *first) > 1.));
+    }
+}
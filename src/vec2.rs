@@ -1,27 +1,30 @@
 use std::ops::*;
 
+use crate::Float;
+
 #[derive(Debug, Clone, Copy, PartialEq)]
-pub struct Vec2 {
-    pub x: f32,
-    pub y: f32,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Vec2<T = f32> {
+    pub x: T,
+    pub y: T,
 }
 
-impl Vec2 {
+impl<T: Float> Vec2<T> {
     /// Returns the distance from this vector to 0,0.
-    pub fn dist(self) -> f32 {
+    pub fn dist(self) -> T {
         (self.x * self.x + self.y * self.y).sqrt()
     }
 
     /// Returns the distance from this vector to 0,0 squared.
-    pub fn dist_sqr(self) -> f32 {
+    pub fn dist_sqr(self) -> T {
         self.x * self.x + self.y * self.y
     }
 
-    pub fn normalize(self) -> Vec2 {
+    pub fn normalize(self) -> Vec2<T> {
         let dist = self.dist();
 
-        if dist == 0. {
-            return Vec2 { x: 0., y: 0. };
+        if dist == T::ZERO {
+            return Vec2 { x: T::ZERO, y: T::ZERO };
         }
 
         Vec2 {
@@ -31,10 +34,10 @@ impl Vec2 {
     }
 }
 
-impl Add for Vec2 {
-    type Output = Vec2;
+impl<T: Float> Add for Vec2<T> {
+    type Output = Vec2<T>;
 
-    fn add(self, other: Vec2) -> Vec2 {
+    fn add(self, other: Vec2<T>) -> Vec2<T> {
         Vec2 {
             x: self.x + other.x,
             y: self.y + other.y,
@@ -42,16 +45,16 @@ impl Add for Vec2 {
     }
 }
 
-impl AddAssign for Vec2 {
-    fn add_assign(&mut self, other: Vec2) {
+impl<T: Float> AddAssign for Vec2<T> {
+    fn add_assign(&mut self, other: Vec2<T>) {
         *self = *self + other;
     }
 }
 
-impl Sub for Vec2 {
-    type Output = Vec2;
+impl<T: Float> Sub for Vec2<T> {
+    type Output = Vec2<T>;
 
-    fn sub(self, other: Vec2) -> Vec2 {
+    fn sub(self, other: Vec2<T>) -> Vec2<T> {
         Vec2 {
             x: self.x - other.x,
             y: self.y - other.y,
@@ -59,16 +62,16 @@ impl Sub for Vec2 {
     }
 }
 
-impl SubAssign for Vec2 {
-    fn sub_assign(&mut self, other: Vec2) {
+impl<T: Float> SubAssign for Vec2<T> {
+    fn sub_assign(&mut self, other: Vec2<T>) {
         *self = *self - other;
     }
 }
 
-impl Mul<f32> for Vec2 {
-    type Output = Vec2;
+impl<T: Float> Mul<T> for Vec2<T> {
+    type Output = Vec2<T>;
 
-    fn mul(self, rhs: f32) -> Vec2 {
+    fn mul(self, rhs: T) -> Vec2<T> {
         Vec2 {
             x: self.x * rhs,
             y: self.y * rhs,
@@ -76,16 +79,16 @@ impl Mul<f32> for Vec2 {
     }
 }
 
-impl MulAssign<f32> for Vec2 {
-    fn mul_assign(&mut self, rhs: f32) {
+impl<T: Float> MulAssign<T> for Vec2<T> {
+    fn mul_assign(&mut self, rhs: T) {
         *self = *self * rhs;
     }
 }
 
-impl Div<f32> for Vec2 {
-    type Output = Vec2;
+impl<T: Float> Div<T> for Vec2<T> {
+    type Output = Vec2<T>;
 
-    fn div(self, rhs: f32) -> Vec2 {
+    fn div(self, rhs: T) -> Vec2<T> {
         Vec2 {
             x: self.x / rhs,
             y: self.y / rhs,
@@ -93,16 +96,16 @@ impl Div<f32> for Vec2 {
     }
 }
 
-impl DivAssign<f32> for Vec2 {
-    fn div_assign(&mut self, rhs: f32) {
+impl<T: Float> DivAssign<T> for Vec2<T> {
+    fn div_assign(&mut self, rhs: T) {
         *self = *self / rhs;
     }
 }
 
-impl Neg for Vec2 {
-    type Output = Vec2;
+impl<T: Float> Neg for Vec2<T> {
+    type Output = Vec2<T>;
 
-    fn neg(self) -> Vec2 {
+    fn neg(self) -> Vec2<T> {
         Vec2 {
             x: -self.x,
             y: -self.y,
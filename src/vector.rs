@@ -0,0 +1,73 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::ops::*;
+
+use crate::{Float, Vec2, Vec3};
+
+/// A position or velocity the solver can run on, so `Simulation` and
+/// `Simulation3` share their particles, cell grid and density relaxation.
+/// Implemented for `Vec2` and `Vec3`.
+pub trait Vector<T: Float>:
+    Copy
+    + Debug
+    + PartialEq
+    + Send
+    + Sync
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<T, Output = Self>
+    + Div<T, Output = Self>
+    + AddAssign
+    + SubAssign
+{
+    /// The key of a cell, one integer per axis.
+    type CellKey: Copy + Debug + Eq + Ord + Hash + Send + Sync;
+
+    const ZERO: Self;
+
+    fn dist(self) -> T;
+    fn normalize(self) -> Self;
+
+    /// The cell containing `self`, for cells `cell_size` wide.
+    fn cell_key(self, cell_size: T) -> Self::CellKey;
+}
+
+fn axis_key<T: Float>(pos: T, cell_size: T) -> i32 {
+    (pos / cell_size).floor().to_f64() as i32
+}
+
+impl<T: Float> Vector<T> for Vec2<T> {
+    type CellKey = (i32, i32);
+
+    const ZERO: Vec2<T> = Vec2 { x: T::ZERO, y: T::ZERO };
+
+    fn dist(self) -> T {
+        Vec2::dist(self)
+    }
+
+    fn normalize(self) -> Vec2<T> {
+        Vec2::normalize(self)
+    }
+
+    fn cell_key(self, cell_size: T) -> (i32, i32) {
+        (axis_key(self.x, cell_size), axis_key(self.y, cell_size))
+    }
+}
+
+impl<T: Float> Vector<T> for Vec3<T> {
+    type CellKey = (i32, i32, i32);
+
+    const ZERO: Vec3<T> = Vec3 { x: T::ZERO, y: T::ZERO, z: T::ZERO };
+
+    fn dist(self) -> T {
+        Vec3::dist(self)
+    }
+
+    fn normalize(self) -> Vec3<T> {
+        Vec3::normalize(self)
+    }
+
+    fn cell_key(self, cell_size: T) -> (i32, i32, i32) {
+        (axis_key(self.x, cell_size), axis_key(self.y, cell_size), axis_key(self.z, cell_size))
+    }
+}